@@ -0,0 +1,122 @@
+//! Baselines record a set of existing findings, so they can be ignored while only new ones are
+//! reported. This makes it possible to adopt the linter on a codebase that already has problems.
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::{ErrorCode, FileResult, LintError};
+
+/// A set of findings that are known about and shouldn't be reported again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    findings: Vec<Entry>,
+}
+
+/// A single finding in a baseline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    file: String,
+    code: ErrorCode,
+    message: String,
+    line: usize,
+}
+
+impl Baseline {
+    /// Builds a baseline containing all the errors found in each file.
+    pub fn new(results: &[FileResult]) -> Baseline {
+        return Baseline{
+            findings: results.iter().flat_map(|result| result.errors.iter().map(move |e| Entry{
+                file: result.path.clone(),
+                code: e.code,
+                message: e.message.clone(),
+                line: e.line,
+            })).collect(),
+        };
+    }
+
+    /// Loads a baseline from the given JSON file.
+    pub fn load(path: &Path) -> Result<Baseline, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read baseline {}: {}", path.display(), e))?;
+        return serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid baseline {}: {}", path.display(), e));
+    }
+
+    /// Writes this baseline to the given file as JSON.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, serde_json::to_string_pretty(self).unwrap() + "\n")
+            .map_err(|e| format!("Failed to write baseline {}: {}", path.display(), e));
+    }
+
+    /// Returns the number of findings in this baseline.
+    pub fn len(&self) -> usize {
+        return self.findings.len();
+    }
+
+    /// Returns true if this baseline has no findings in it.
+    pub fn is_empty(&self) -> bool {
+        return self.findings.is_empty();
+    }
+
+    /// Removes any errors that are in the baseline from the errors found in a file.
+    /// Errors match an entry with the same code and message; since other changes to the file can
+    /// move them around, the line only decides between several such entries, with the nearest
+    /// one winning. Each entry only matches a single error, so new copies of an existing finding
+    /// are still reported.
+    pub(crate) fn filter(&self, file: &str, errors: Vec<LintError>) -> Vec<LintError> {
+        let mut entries = self.findings.iter().filter(|entry| entry.file == file).collect::<Vec<_>>();
+        return errors.into_iter().filter(|e| {
+            let nearest = entries.iter().enumerate()
+                .filter(|(_, entry)| entry.code == e.code && entry.message == e.message)
+                .min_by_key(|(_, entry)| (entry.line as isize - e.line as isize).abs())
+                .map(|(i, _)| i);
+            match nearest {
+                Some(i) => {
+                    entries.remove(i);
+                    false
+                },
+                None => true,
+            }
+        }).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let baseline = Baseline::new(&[
+            FileResult::new("a.sql", vec![
+                LintError::new(ErrorCode::NonConcurrentIndex, "Index a is created without CONCURRENTLY").at(3, 1),
+                LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(5, 1),
+                LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(20, 1),
+            ]),
+        ]);
+        let errors = baseline.filter("a.sql", vec![
+            // Moved down a few lines by a new statement above it.
+            LintError::new(ErrorCode::NonConcurrentIndex, "Index a is created without CONCURRENTLY").at(6, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "Index b is created without CONCURRENTLY").at(3, 1),
+            LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(8, 1),
+            LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(23, 1),
+            LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(30, 1),
+        ]);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(3, 1),
+            LintError::new(ErrorCode::DropColumn, "").at(30, 1),
+        ], errors);
+        let errors = baseline.filter("b.sql", vec![LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(5, 1)]);
+        assert_eq!(1, errors.len());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let baseline = Baseline::new(&[
+            FileResult::new("a.sql", vec![LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(5, 1)]),
+        ]);
+        let json = serde_json::to_string(&baseline).unwrap();
+        assert_eq!(r#"{"findings":[{"file":"a.sql","code":"DropColumn","message":"Column b is dropped","line":5}]}"#, json);
+        let parsed: Baseline = serde_json::from_str(&json).unwrap();
+        assert_eq!(baseline.findings, parsed.findings);
+    }
+}
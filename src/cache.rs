@@ -0,0 +1,116 @@
+//! An on-disk cache of the errors found in files, so unchanged files don't need to be re-parsed.
+//! Entries are keyed by a hash of the file's contents, the linter version and the config, so any
+//! change to one of those means a cache miss rather than stale results.
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::{Config, LintError, VERSION};
+
+/// The version of the format that entries are stored in. It's part of the key, so entries written
+/// in an older format are never read back.
+const FORMAT: &str = "2";
+
+/// The parameters of the 64-bit FNV-1a hash used for cache keys, which unlike the standard
+/// library's hashers is guaranteed not to change.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A single cached error. The table it's attributed to isn't part of the error's serialised form
+/// (which is also what JSON output uses), so it's stored alongside it.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    error: LintError,
+    table: Option<String>,
+}
+
+/// A directory of cached results.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub(crate) fn new(dir: PathBuf) -> Cache {
+        return Cache{dir: dir};
+    }
+
+    /// Returns the key that results for the given contents and config are stored under.
+    /// It's a hash of things with a stable form (the config is serialised to JSON rather than
+    /// using its Debug output), so it stays the same between runs and builds of the linter.
+    pub(crate) fn key(contents: &str, config: &Config) -> String {
+        let config = serde_json::to_string(config).unwrap();
+        let mut hash = FNV_OFFSET_BASIS;
+        for part in &[FORMAT, VERSION, config.as_str(), contents] {
+            // Include the length of each part so they can't run into one another.
+            for byte in part.len().to_le_bytes().iter().chain(part.as_bytes()) {
+                hash = (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+            }
+        }
+        return format!("{:016x}", hash);
+    }
+
+    /// Returns the cached results for the given key, if there are any.
+    /// Entries that can't be read or parsed are treated as misses.
+    pub(crate) fn get(&self, key: &str) -> Option<Vec<LintError>> {
+        let contents = fs::read_to_string(self.path(key)).ok()?;
+        return match serde_json::from_str::<Vec<Entry>>(&contents) {
+            Ok(entries) => Some(entries.into_iter().map(|entry| LintError{table: entry.table, ..entry.error}).collect()),
+            Err(e) => {
+                warn!("Ignoring invalid cache entry {}: {}", key, e);
+                None
+            },
+        };
+    }
+
+    /// Stores the given results under the given key. Failures are logged but otherwise ignored,
+    /// since the cache is only an optimisation.
+    pub(crate) fn put(&self, key: &str, errors: &[LintError]) {
+        let entries = errors.iter().map(|e| Entry{error: e.clone(), table: e.table.clone()}).collect::<Vec<_>>();
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(self.path(key), serde_json::to_string(&entries).unwrap()));
+        if let Err(e) = result {
+            warn!("Failed to write cache entry to {}: {}", self.dir.display(), e);
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        return self.dir.join(format!("{}.json", key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorCode;
+
+    #[test]
+    fn test_key() {
+        let config = Config::default();
+        let key = Cache::key("CREATE INDEX a ON b (c);", &config);
+        assert_eq!(key, Cache::key("CREATE INDEX a ON b (c);", &config));
+        assert_ne!(key, Cache::key("CREATE INDEX a ON b (d);", &config));
+        let config = Config::parse(r#"disabled = ["NonConcurrentIndex"]"#).unwrap();
+        assert_ne!(key, Cache::key("CREATE INDEX a ON b (c);", &config));
+        let mut selected = Config::default();
+        selected.select(vec![ErrorCode::DropColumn]);
+        assert_ne!(key, Cache::key("CREATE INDEX a ON b (c);", &selected));
+    }
+
+    #[test]
+    fn test_get_put() {
+        let dir = std::env::temp_dir().join(format!("sql-perf-linter-cache-test-{}", std::process::id()));
+        let cache = Cache::new(dir.clone());
+        assert!(cache.get("0123456789abcdef").is_none());
+        let errors = vec![LintError{
+            table: Some("b".to_string()),
+            ..LintError::new(ErrorCode::NonConcurrentIndex, "Index a is created without CONCURRENTLY").at(2, 3)
+        }];
+        cache.put("0123456789abcdef", &errors);
+        let cached = cache.get("0123456789abcdef").unwrap();
+        assert_eq!(errors, cached);
+        assert_eq!("Index a is created without CONCURRENTLY", cached[0].message());
+        assert_eq!(Some("b"), cached[0].table());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
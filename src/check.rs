@@ -0,0 +1,266 @@
+//! The rules run against each statement, and the Linter that runs them.
+//! The built-in rules make up the default set; organisation-specific ones can be added to it
+//! through the library API without having to fork the linter. The built-in rules live in the
+//! submodules, grouped by what they're about, along with the functions that do their work.
+use std::fmt;
+use std::path::Path;
+use sqlparser::ast;
+use crate::{Config, ErrorCode, FileState, LintError, LinterContext};
+
+pub(crate) mod destructive;
+pub(crate) mod index;
+pub(crate) mod tables;
+pub(crate) mod transaction;
+pub(crate) mod unparsed;
+pub(crate) mod write;
+
+/// A rule that's run against every statement the linter sees.
+/// Rules that need to remember things between statements should use what the context knows,
+/// since the same rule is shared between all the files linted in parallel.
+pub trait Rule: Send + Sync {
+    /// Returns any problems found with the given statement.
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError>;
+
+    /// Returns any problems found in the SQL of the given statement. This is called before check,
+    /// and for statements sqlparser couldn't parse as well as the ones it could.
+    fn check_sql(&self, _sql: &str, _context: &mut CheckContext) -> Vec<LintError> {
+        return Vec::new();
+    }
+
+    /// Returns the problems found with a statement sqlparser couldn't parse, or None if this rule
+    /// doesn't recognise it. Statements that no rule recognises are reported as syntax errors.
+    fn check_unparsed(&self, _sql: &str, _context: &mut CheckContext) -> Option<Vec<LintError>> {
+        return None;
+    }
+
+    /// Returns any problems with the file as a whole, once all of its statements are checked.
+    fn finish(&self, _context: &mut CheckContext) -> Vec<LintError> {
+        return Vec::new();
+    }
+}
+
+/// What a rule knows about besides the statement itself.
+#[derive(Debug)]
+pub struct CheckContext<'a> {
+    /// The configuration being linted with.
+    pub config: &'a Config,
+    /// State carried over from the statements linted before this one.
+    pub context: &'a LinterContext,
+    /// The line the statement starts on.
+    pub line: usize,
+    /// What the built-in rules track across the statements in the file.
+    pub(crate) file: &'a mut FileState,
+}
+
+/// A set of rules to lint SQL with.
+pub struct Linter {
+    checks: Vec<Box<dyn Rule>>,
+}
+
+impl fmt::Debug for Linter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.debug_struct("Linter").field("checks", &self.checks.len()).finish();
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Linter {
+        return Linter::new();
+    }
+}
+
+impl Linter {
+    /// Returns a linter with all the built-in rules.
+    pub fn new() -> Linter {
+        return Linter{checks: default_checks()};
+    }
+
+    /// Returns a linter without any rules, for building up a set from scratch.
+    /// Statements that can't be parsed are still reported as syntax errors, as are unknown rules
+    /// in suppression comments, but nothing else is.
+    pub fn empty() -> Linter {
+        return Linter{checks: Vec::new()};
+    }
+
+    /// Returns this linter with the given rule added to it.
+    pub fn with_check(mut self, check: impl Rule + 'static) -> Linter {
+        self.checks.push(Box::new(check));
+        return self;
+    }
+
+    /// Lints the given SQL and returns any errors found, applying the given configuration.
+    pub fn lint_str(&self, sql: &str, config: &Config) -> Vec<LintError> {
+        return crate::lint_contents(self, sql.to_string(), config, &mut LinterContext::new());
+    }
+
+    /// Lints the given file and returns any errors found, applying the given configuration.
+    /// If the path is "-", the SQL is read from stdin.
+    pub fn lint_file(&self, path: &Path, config: &Config) -> Vec<LintError> {
+        return crate::lint_errors_in(self, path, config, &mut LinterContext::new());
+    }
+
+    /// Runs all the rules against the given statement.
+    pub(crate) fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return self.checks.iter().flat_map(|check| check.check(stmt, context)).collect();
+    }
+
+    /// Runs all the rules against the SQL of the given statement.
+    pub(crate) fn check_sql(&self, sql: &str, context: &mut CheckContext) -> Vec<LintError> {
+        return self.checks.iter().flat_map(|check| check.check_sql(sql, context)).collect();
+    }
+
+    /// Runs all the rules against a statement sqlparser couldn't parse. Returns None if none of
+    /// them recognised it.
+    pub(crate) fn check_unparsed(&self, sql: &str, context: &mut CheckContext) -> Option<Vec<LintError>> {
+        let mut recognised = false;
+        let mut errors = Vec::new();
+        for check in &self.checks {
+            if let Some(found) = check.check_unparsed(sql, context) {
+                recognised = true;
+                errors.extend(found);
+            }
+        }
+        return if recognised { Some(errors) } else { None };
+    }
+
+    /// Runs all the rules against the file as a whole, once all its statements are checked.
+    pub(crate) fn finish(&self, context: &mut CheckContext) -> Vec<LintError> {
+        return self.checks.iter().flat_map(|check| check.finish(context)).collect();
+    }
+}
+
+/// Returns the built-in rules, i.e. the ones a new Linter has.
+pub fn default_checks() -> Vec<Box<dyn Rule>> {
+    return vec![
+        Box::new(tables::AlterTableCheck),
+        Box::new(tables::CreateTableCheck),
+        Box::new(index::IndexCheck),
+        Box::new(index::IndexMethodCheck),
+        Box::new(write::WriteCheck),
+        Box::new(destructive::DestructiveCheck),
+        Box::new(destructive::DropIfExistsCheck),
+        Box::new(transaction::TransactionCheck),
+        Box::new(transaction::LockTimeoutCheck),
+        Box::new(write::LongWriteCheck),
+        Box::new(transaction::TransactionIncompatibleCheck),
+        Box::new(index::DuplicateIndexCheck),
+        Box::new(write::LoadAfterLockCheck),
+        Box::new(BackfillCheck),
+        Box::new(unparsed::UnparsedCheck),
+        Box::new(EmptyMigrationCheck),
+    ];
+}
+
+/// Checks for columns backfilled after they were made NOT NULL, possibly in an earlier file.
+pub(crate) struct BackfillCheck;
+
+impl Rule for BackfillCheck {
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return context.context.lint_backfill(stmt);
+    }
+}
+
+/// Checks for files that don't do anything.
+pub(crate) struct EmptyMigrationCheck;
+
+impl Rule for EmptyMigrationCheck {
+    fn check(&self, _: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return Vec::new();
+    }
+
+    fn finish(&self, context: &mut CheckContext) -> Vec<LintError> {
+        if context.file.has_statements {
+            return Vec::new();
+        }
+        return vec![LintError::new(ErrorCode::EmptyMigration,
+            "This file doesn't contain any statements (other than starting or ending a transaction). Empty migrations are usually a mistake.")];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use sqlparser::dialect::PostgreSqlDialect;
+    use sqlparser::parser::Parser;
+    use crate::lint_errors;
+
+    /// A rule that doesn't allow any tables to be created.
+    struct NoCreateTable;
+
+    impl Rule for NoCreateTable {
+        fn check(&self, stmt: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+            return match stmt {
+                ast::Statement::CreateTable{name, ..} =>
+                    vec![LintError::new(ErrorCode::Custom, format!("Table {} is created; ask the DBAs first.", name).as_str())],
+                _ => Vec::new(),
+            };
+        }
+    }
+
+    #[test]
+    fn test_custom_check() {
+        let linter = Linter::new().with_check(NoCreateTable);
+        let errors = linter.lint_str("CREATE TABLE guilds (id BIGINT);\nCREATE INDEX name_idx ON characters (name);", &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::Custom, "").at(1, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(2, 1),
+        ], errors);
+        assert_eq!("Table guilds is created; ask the DBAs first.", errors[0].message());
+    }
+
+    #[test]
+    fn test_empty() {
+        let linter = Linter::empty().with_check(NoCreateTable);
+        let errors = linter.lint_str("CREATE TABLE guilds (id BIGINT);\nCREATE INDEX name_idx ON characters (name);", &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::Custom, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_empty_has_no_builtin_rules() {
+        // Not even the ones that track state across the file or work on the SQL.
+        let config = Config::parse(r#"enabled = ["MissingLockTimeout", "TransactionIncompatible", "EmptyMigration"]"#).unwrap();
+        let sql = "BEGIN;\nCREATE INDEX CONCURRENTLY name_idx ON characters (name);\nCOMMIT;\nCLUSTER characters;";
+        assert_eq!(vec![LintError::new(ErrorCode::SyntaxError, "").at(4, 1)], Linter::empty().lint_str(sql, &config));
+        assert_eq!(0, Linter::empty().lint_str("", &config).len());
+    }
+
+    #[test]
+    fn test_builtin_check() {
+        let stmts = Parser::parse_sql(&PostgreSqlDialect{}, "DELETE FROM characters; DELETE FROM characters WHERE id = 1;".to_string()).unwrap();
+        let config = Config::default();
+        let linter = LinterContext::new();
+        let mut file = FileState::new();
+        let mut context = CheckContext{config: &config, context: &linter, line: 1, file: &mut file};
+        assert_eq!(vec![LintError::new(ErrorCode::UnboundedWrite, "")], write::WriteCheck.check(&stmts[0], &mut context));
+        assert_eq!(0, write::WriteCheck.check(&stmts[1], &mut context).len());
+    }
+
+    #[test]
+    fn test_builtin_file_state() {
+        let stmts = Parser::parse_sql(&PostgreSqlDialect{}, "BEGIN; CREATE INDEX CONCURRENTLY name_idx ON characters (name);".to_string()).unwrap();
+        let config = Config::default();
+        let linter = LinterContext::new();
+        let mut file = FileState::new();
+        let mut context = CheckContext{config: &config, context: &linter, line: 1, file: &mut file};
+        assert_eq!(0, transaction::TransactionCheck.check(&stmts[0], &mut context).len());
+        assert_eq!(vec![LintError::new(ErrorCode::ConcurrentIndexInTransaction, "")], transaction::TransactionCheck.check(&stmts[1], &mut context));
+    }
+
+    #[test]
+    fn test_lint_empty_migration() {
+        let config = Config::parse(r#"enabled = ["EmptyMigration"]"#).unwrap();
+        for file in &["test_data/empty.sql", "test_data/comments_only.sql", "test_data/empty_transaction.sql"] {
+            let errors = lint_errors(&PathBuf::from(file), &config);
+            assert_eq!(vec![LintError::new(ErrorCode::EmptyMigration, "").at(1, 1)], errors, "{}", file);
+        }
+        let errors = lint_errors(&PathBuf::from("test_data/drop_column.sql"), &config);
+        assert!(errors.iter().all(|e| e.code != ErrorCode::EmptyMigration));
+    }
+
+    #[test]
+    fn test_lint_empty_migration_disabled_by_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/comments_only.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+}
@@ -0,0 +1,81 @@
+//! Rules for statements that throw data away.
+use sqlparser::ast;
+use crate::{ErrorCode, LintError};
+use super::{CheckContext, Rule};
+
+/// Checks for statements that throw data away.
+pub(crate) struct DestructiveCheck;
+
+impl Rule for DestructiveCheck {
+    fn check(&self, stmt: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return match stmt {
+            ast::Statement::Drop{object_type: ast::ObjectType::Table, names, ..} => names.iter().map(|name| {
+                LintError::new(ErrorCode::DestructiveStatement, format!(
+                    "Table {} is dropped. Its data cannot be recovered afterwards, so make sure this is intended and nothing still uses it.", name).as_str())
+            }).collect(),
+            ast::Statement::Truncate{table_name, ..} =>
+                vec![LintError::new(ErrorCode::DestructiveStatement, format!(
+                    "Table {} is truncated. Its data cannot be recovered afterwards, and this takes an ACCESS EXCLUSIVE lock on the table.", table_name).as_str())],
+            _ => Vec::new(),
+        };
+    }
+}
+
+/// Checks that objects are only dropped if they exist, so migrations can be run again.
+pub(crate) struct DropIfExistsCheck;
+
+impl Rule for DropIfExistsCheck {
+    fn check(&self, stmt: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return match stmt {
+            ast::Statement::Drop{object_type, if_exists: false, names, ..} => names.iter().map(|name| {
+                LintError::new(ErrorCode::DropWithoutIfExists, format!(
+                    "DROP {} {} has no IF EXISTS, so the migration is not idempotent; running it again fails once {} is gone.", object_type, name, name).as_str())
+            }).collect(),
+            _ => Vec::new(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::{lint_errors, Config};
+
+    #[test]
+    fn test_lint_drop_table() {
+        let errors = lint_errors(&PathBuf::from("test_data/drop_table.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DestructiveStatement, "").at(1, 1),
+            LintError::new(ErrorCode::DestructiveStatement, "").at(1, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_truncate() {
+        let errors = lint_errors(&PathBuf::from("test_data/truncate.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::DestructiveStatement, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_drop_without_if_exists() {
+        let config = Config::parse(r#"enabled = ["DropWithoutIfExists"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/drop_without_if_exists.sql"), &config)
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::DropWithoutIfExists)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DropWithoutIfExists, "").at(1, 1),
+            LintError::new(ErrorCode::DropWithoutIfExists, "").at(2, 1),
+        ], errors);
+        assert!(errors[0].message().contains("not idempotent"));
+        let errors = lint_errors(&PathBuf::from("test_data/drop_with_if_exists.sql"), &config);
+        assert!(errors.iter().all(|e| e.code != ErrorCode::DropWithoutIfExists));
+    }
+
+    #[test]
+    fn test_lint_drop_without_if_exists_disabled_by_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/drop_without_if_exists.sql"), &Config::default());
+        assert!(errors.iter().all(|e| e.code != ErrorCode::DropWithoutIfExists));
+    }
+}
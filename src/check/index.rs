@@ -0,0 +1,340 @@
+//! Rules for creating and dropping indexes.
+use sqlparser::ast;
+use crate::{index_tokens, words, Config, ErrorCode, LintError};
+use super::{CheckContext, Rule};
+
+/// Checks that indexes are created and dropped without blocking writes.
+pub(crate) struct IndexCheck;
+
+impl Rule for IndexCheck {
+    fn check(&self, stmt: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return match stmt {
+            ast::Statement::CreateIndex{name, table_name, if_not_exists, concurrently, ..} =>
+                lint_create_index(name, table_name, *if_not_exists, *concurrently).into_iter().map(|e| e.on_table(table_name)).collect(),
+            ast::Statement::Drop{object_type: ast::ObjectType::Index, names, concurrently, ..} =>
+                lint_drop_index(names, *concurrently),
+            _ => Vec::new(),
+        };
+    }
+}
+
+/// Checks that indexes use the method configured for their columns.
+pub(crate) struct IndexMethodCheck;
+
+impl Rule for IndexMethodCheck {
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return lint_index_method(stmt, context.config);
+    }
+}
+
+/// Checks for indexes that are the same as one created earlier in the file.
+pub(crate) struct DuplicateIndexCheck;
+
+impl Rule for DuplicateIndexCheck {
+    fn check(&self, _: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return Vec::new();
+    }
+
+    fn check_sql(&self, sql: &str, context: &mut CheckContext) -> Vec<LintError> {
+        return lint_duplicate_index(sql, context.line, &mut context.file.indexes);
+    }
+}
+
+/// Returns true if the given words (following CREATE) create an index without naming it.
+pub(crate) fn is_unnamed_index(words: &[&str]) -> bool {
+    let words = match words {
+        ["UNIQUE", rest @ ..] => rest,
+        _ => words,
+    };
+    return match words {
+        ["INDEX", "ON", ..] | ["INDEX", "CONCURRENTLY", "ON", ..] => true,
+        _ => false,
+    };
+}
+
+/// Returns true if the given words (following CREATE) create an index CONCURRENTLY.
+pub(crate) fn is_concurrent_index(words: &[&str]) -> bool {
+    return match words {
+        ["INDEX", "CONCURRENTLY", ..] | ["UNIQUE", "INDEX", "CONCURRENTLY", ..] => true,
+        _ => false,
+    };
+}
+
+/// Returns true if the given words (following CREATE) create an index.
+pub(crate) fn is_create_index(words: &[&str]) -> bool {
+    return match words {
+        ["INDEX", ..] | ["UNIQUE", "INDEX", ..] => true,
+        _ => false,
+    };
+}
+
+fn lint_create_index(name: &ast::ObjectName, table: &ast::ObjectName, if_not_exists: bool, concurrently: bool) -> Vec<LintError> {
+    let mut errors = Vec::new();
+    let unnamed = name.0.is_empty();
+    if unnamed {
+        errors.push(unnamed_index(Some(table)));
+    }
+    if concurrently {
+        errors.push(LintError::new(ErrorCode::ConcurrentIndex, format!(
+            "Index {} on {} is created CONCURRENTLY, which doesn't block writes to the table.", if unnamed { "(unnamed)".to_string() } else { name.to_string() }, table).as_str()));
+    } else {
+        let mut message = if unnamed {
+            format!("An index on {} is created without CONCURRENTLY.", table)
+        } else {
+            format!("Index {} on {} is created without CONCURRENTLY.", name, table)
+        };
+        message.push_str(" This requires holding an exclusive table lock while the index is built, which can cause downtime.");
+        if if_not_exists {
+            message.push_str(" IF NOT EXISTS makes this safe to re-run, but the lock is still taken whenever the index doesn't exist yet.");
+        }
+        errors.push(LintError::new(ErrorCode::NonConcurrentIndex, &message));
+    }
+    return errors;
+}
+
+/// Checks that an index is created with the method configured for the columns it's on.
+/// sqlparser doesn't keep the method, so it's read back out of the statement's SQL.
+fn lint_index_method(stmt: &ast::Statement, config: &Config) -> Vec<LintError> {
+    return match stmt {
+        ast::Statement::CreateIndex{..} => check_index_method(&stmt.to_string(), config),
+        _ => Vec::new(),
+    };
+}
+
+/// Returns an error for each column indexed by the given CREATE INDEX statement that's configured
+/// to be indexed with a different method than the one it uses.
+pub(crate) fn check_index_method(sql: &str, config: &Config) -> Vec<LintError> {
+    let IndexDefinition{method, columns, ..} = match index_definition(sql) {
+        Some(def) => def,
+        None => return Vec::new(),
+    };
+    return columns.iter().filter_map(|column| {
+        let expected = config.index_method(column)?;
+        if expected.eq_ignore_ascii_case(&method) {
+            return None;
+        }
+        Some(LintError::new(ErrorCode::IndexMethod, format!(
+            "Column {} is indexed using {}, but indexes on it are configured to use {}.", column, method, expected).as_str()))
+    }).collect();
+}
+
+/// The parts of a CREATE INDEX statement that decide what it indexes.
+#[derive(Debug, PartialEq)]
+pub(crate) struct IndexDefinition {
+    table: String,
+    unique: bool,
+    /// The index method, btree if the statement doesn't say.
+    method: String,
+    /// The names in the list of columns. For expressions, that's every name in the expression.
+    columns: Vec<String>,
+    /// True if the index has a WHERE clause, i.e. only covers some of the rows.
+    partial: bool,
+}
+
+/// Reads the definition of an index from the SQL of a CREATE INDEX statement.
+fn index_definition(sql: &str) -> Option<IndexDefinition> {
+    let tokens = index_tokens(sql);
+    let on = tokens.iter().position(|t| t.eq_ignore_ascii_case("ON"))?;
+    let open = on + tokens[on..].iter().position(|t| t == "(")?;
+    let head = &tokens[on + 1..open];
+    let using = head.iter().position(|t| t.eq_ignore_ascii_case("USING"));
+    let method = match using {
+        Some(i) => head.get(i + 1)?.to_lowercase(),
+        None => "btree".to_string(),
+    };
+    let table = head[..using.unwrap_or(head.len())].iter()
+        .filter(|t| !t.eq_ignore_ascii_case("ONLY"))
+        .map(|t| t.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(".");
+    let mut columns: Vec<String> = Vec::new();
+    let mut depth = 0;
+    let mut close = tokens.len();
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token.as_str() {
+            "(" => depth += 1,
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    close = i;
+                    break;
+                }
+            },
+            _ => {
+                let token = token.to_lowercase();
+                let keyword = ["asc", "desc", "nulls", "first", "last", "collate"].contains(&token.as_str());
+                if !keyword && !columns.contains(&token) {
+                    columns.push(token);
+                }
+            },
+        }
+    }
+    return Some(IndexDefinition{
+        table: table,
+        unique: tokens[..on].iter().any(|t| t.eq_ignore_ascii_case("UNIQUE")),
+        method: method,
+        columns: columns,
+        partial: tokens.iter().skip(close).any(|t| t.eq_ignore_ascii_case("WHERE")),
+    });
+}
+
+/// Lints a statement for creating an index that's the same as one created earlier in the file,
+/// recording it in the indexes created so far along with the line it's on. Partial indexes are
+/// left alone, since we can't tell whether their predicates are the same.
+fn lint_duplicate_index(sql: &str, line: usize, indexes: &mut Vec<(IndexDefinition, usize)>) -> Vec<LintError> {
+    let words = words(sql);
+    let words = words.iter().map(|w| w.as_str()).collect::<Vec<_>>();
+    let def = match words.as_slice() {
+        ["CREATE", rest @ ..] if is_create_index(rest) => index_definition(sql),
+        _ => None,
+    };
+    let def = match def {
+        Some(def) if !def.partial => def,
+        _ => return Vec::new(),
+    };
+    if let Some((_, earlier)) = indexes.iter().find(|(seen, _)| *seen == def) {
+        return vec![LintError::new(ErrorCode::DuplicateIndex, format!(
+            "This index on {} has the same definition as the one created on line {}. The second one only costs time to build and space to store, and slows down writes to the table.", def.table, earlier).as_str())];
+    }
+    indexes.push((def, line));
+    return Vec::new();
+}
+
+pub(crate) fn unnamed_index(table: Option<&ast::ObjectName>) -> LintError {
+    let on = table.map_or(String::new(), |table| format!(" on {}", table));
+    return LintError::new(ErrorCode::UnnamedIndex, format!(
+        "An index{} is created without a name. Postgres will generate one, which makes it harder to script dropping or rebuilding it later.", on).as_str());
+}
+
+fn lint_drop_index(names: &[ast::ObjectName], concurrently: bool) -> Vec<LintError> {
+    return if concurrently {
+        Vec::new()
+    } else {
+        names.iter().map(|name| LintError::new(ErrorCode::NonConcurrentIndexDrop, format!(
+            "Index {} is dropped without CONCURRENTLY. This requires holding an exclusive table lock while the index is dropped, which can cause downtime.", name).as_str())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::{lint_errors, lint_str, Severity};
+
+    #[test]
+    fn test_lint_create_index_sync() {
+        let errors = lint_errors(&PathBuf::from("test_data/create_index_sync.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_concurrent_index() {
+        let config = Config::parse(r#"enabled = ["ConcurrentIndex"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/create_index_async.sql"), &config);
+        assert_eq!(vec![LintError::new(ErrorCode::ConcurrentIndex, "").at(1, 1)], errors);
+        assert_eq!(Severity::Info, errors[0].severity());
+        let errors = lint_errors(&PathBuf::from("test_data/create_index_sync.sql"), &config);
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_create_index_if_not_exists() {
+        let errors = lint_str("CREATE INDEX IF NOT EXISTS name_idx ON characters (name);");
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)], errors);
+        assert!(errors[0].message().starts_with("Index name_idx on characters is created without CONCURRENTLY."));
+        assert!(errors[0].message().contains("IF NOT EXISTS"));
+    }
+
+    #[test]
+    fn test_lint_unnamed_index() {
+        let config = Config::parse(r#"enabled = ["UnnamedIndex"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/unnamed_index.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::UnnamedIndex, "").at(2, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_unnamed_index_disabled_by_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/unnamed_index.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_index_method() {
+        let config = Config::parse(r#"
+            enabled = ["IndexMethod"]
+
+            [index_methods]
+            "*_tsv" = "gin"
+        "#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/index_method.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::IndexMethod, "").at(2, 1),
+        ], errors);
+        assert_eq!("Column search_tsv is indexed using btree, but indexes on it are configured to use gin.", errors[0].message());
+    }
+
+    #[test]
+    fn test_lint_index_method_disabled_by_default() {
+        let config = Config::parse("[index_methods]\n\"*_tsv\" = \"gin\"").unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/index_method.sql"), &config);
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_index_definition() {
+        let def = index_definition("CREATE INDEX a ON b USING GIN (body_tsv)").unwrap();
+        assert_eq!("gin", def.method);
+        assert_eq!(vec!["body_tsv".to_string()], def.columns);
+        let def = index_definition("CREATE UNIQUE INDEX a ON public.b (name, level DESC NULLS LAST)").unwrap();
+        assert_eq!(IndexDefinition{
+            table: "public.b".to_string(),
+            unique: true,
+            method: "btree".to_string(),
+            columns: vec!["name".to_string(), "level".to_string()],
+            partial: false,
+        }, def);
+        let def = index_definition("CREATE INDEX ON ONLY b USING gin (to_tsvector('english', body)) WHERE body IS NOT NULL").unwrap();
+        assert_eq!("b", def.table);
+        assert_eq!(vec!["to_tsvector".to_string(), "body".to_string()], def.columns);
+        assert!(def.partial);
+        assert_eq!(None, index_definition("CREATE INDEX a"));
+    }
+
+    #[test]
+    fn test_is_unnamed_index() {
+        assert!(is_unnamed_index(&["INDEX", "ON", "CHARACTERS"]));
+        assert!(is_unnamed_index(&["UNIQUE", "INDEX", "CONCURRENTLY", "ON", "CHARACTERS"]));
+        assert!(!is_unnamed_index(&["INDEX", "NAME_IDX", "ON", "CHARACTERS"]));
+        assert!(!is_unnamed_index(&["TABLE", "CHARACTERS"]));
+    }
+
+    #[test]
+    fn test_lint_create_index_async() {
+        let errors = lint_errors(&PathBuf::from("test_data/create_index_async.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_drop_index_sync() {
+        let errors = lint_errors(&PathBuf::from("test_data/drop_index_sync.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndexDrop, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_drop_index_async() {
+        let errors = lint_errors(&PathBuf::from("test_data/drop_index_async.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_duplicate_index() {
+        let errors = lint_errors(&PathBuf::from("test_data/duplicate_index.sql"), &Config::default())
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::DuplicateIndex)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![LintError::new(ErrorCode::DuplicateIndex, "").at(4, 1)], errors);
+        assert!(errors[0].message().contains("created on line 1"));
+    }
+}
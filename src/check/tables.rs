@@ -0,0 +1,518 @@
+//! Rules for the columns and constraints of new and existing tables.
+use sqlparser::ast;
+use crate::{ErrorCode, LintError, PgVersion};
+use super::{CheckContext, Rule};
+
+/// Checks changes to existing tables made by ALTER TABLE.
+pub(crate) struct AlterTableCheck;
+
+impl Rule for AlterTableCheck {
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return match stmt {
+            ast::Statement::AlterTable{name, operation} =>
+                lint_alter_table(name, operation, context.config.pg_version()).into_iter().map(|e| e.on_table(name)).collect(),
+            _ => Vec::new(),
+        };
+    }
+}
+
+/// Checks the columns of new tables.
+pub(crate) struct CreateTableCheck;
+
+impl Rule for CreateTableCheck {
+    fn check(&self, stmt: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return match stmt {
+            ast::Statement::CreateTable{columns, ..} => lint_create_table(columns),
+            _ => Vec::new(),
+        };
+    }
+}
+
+/// Lints a single ALTER TABLE operation on the given table. Errors about a column are marked as being
+/// about it, so they can be positioned at it.
+pub(crate) fn lint_alter_table(name: &ast::ObjectName, operation: &ast::AlterTableOperation, version: PgVersion) -> Vec<LintError> {
+    let column = match operation {
+        ast::AlterTableOperation::AddColumn(def) => Some(&def.name),
+        ast::AlterTableOperation::AlterColumn{column_name, ..} | ast::AlterTableOperation::DropColumn{column_name, ..} => Some(column_name),
+        ast::AlterTableOperation::RenameColumn{column, ..} => Some(column),
+        _ => None,
+    };
+    let errors = match operation {
+        ast::AlterTableOperation::AddColumn(def) => lint_add_column(name, def, version),
+        ast::AlterTableOperation::AlterColumn{column_name, op} => lint_alter_column(name, column_name, op),
+        ast::AlterTableOperation::AddConstraint(constraint) => lint_add_constraint(name, constraint),
+        ast::AlterTableOperation::DropColumn{column_name, ..} =>
+            vec![LintError::new(ErrorCode::DropColumn, format!("Column {} on table {} is being dropped. Its data cannot be recovered afterwards, so make sure application code has stopped referencing it first.", column_name, name).as_str())],
+        ast::AlterTableOperation::RenameColumn{column, to} =>
+            vec![LintError::new(ErrorCode::RenameColumn, format!("Column {} on table {} is being renamed to {}, this is never a safe operation.", column, name, to).as_str())],
+        ast::AlterTableOperation::RenameTable{to} =>
+            vec![LintError::new(ErrorCode::RenameTable, format!("Table {} is being renamed to {}. The rename itself is instant, but any application code still using the old name will break as soon as it runs; deploy code that can handle both names first.", name, to).as_str())],
+        _ => Vec::new(),
+    };
+    return match column {
+        Some(column) => errors.into_iter().map(|e| e.on_column(column)).collect(),
+        None => errors,
+    };
+}
+
+/// The pseudo-types that create a sequence for a column.
+const SERIAL_TYPES: &[&str] = &["smallserial", "serial", "bigserial", "serial2", "serial4", "serial8"];
+
+/// Lints the columns of a newly created table. Since the table is empty there are no rows to
+/// rewrite, so only the checks that apply to every column definition are relevant here;
+/// NOT NULL and defaults are fine.
+fn lint_create_table(columns: &[ast::ColumnDef]) -> Vec<LintError> {
+    return columns.iter().flat_map(|def| lint_column(def).into_iter().map(move |e| e.on_column(&def.name))).collect();
+}
+
+/// Lints a column definition, either in a new table or one added to an existing table.
+/// Checks that only matter when there are existing rows belong in lint_add_column instead.
+fn lint_column(def: &ast::ColumnDef) -> Vec<LintError> {
+    return lint_serial_column(def).into_iter().chain(lint_unbounded_column(def)).collect();
+}
+
+fn lint_serial_column(def: &ast::ColumnDef) -> Option<LintError> {
+    let data_type = def.data_type.to_string().to_lowercase();
+    return if SERIAL_TYPES.contains(&data_type.as_str()) {
+        Some(LintError::new(ErrorCode::PreferIdentity, format!(
+            "Column {} has type {}. Serial types have awkward ownership and permission behaviour for their sequences; consider using GENERATED ALWAYS AS IDENTITY instead.", def.name, data_type).as_str()))
+    } else {
+        None
+    };
+}
+
+fn lint_unbounded_column(def: &ast::ColumnDef) -> Option<LintError> {
+    return match def.data_type {
+        ast::DataType::Varchar(None) | ast::DataType::Text => Some(LintError::new(ErrorCode::UnboundedVarchar, format!(
+            "Column {} has type {} without a maximum length. This is a policy rule; if the column really should be unbounded, suppress it with a comment explaining why.", def.name, def.data_type).as_str())),
+        _ => None,
+    };
+}
+
+fn lint_add_column(table: &ast::ObjectName, def: &ast::ColumnDef, version: PgVersion) -> Vec<LintError> {
+    let not_null = def.options.iter().any(|opt| opt.option == ast::ColumnOption::NotNull);
+    let default = def.options.iter().find_map(|opt| match &opt.option {
+        ast::ColumnOption::Default(expr) => Some(expr),
+        _ => None,
+    });
+    let mut errors = lint_column(def);
+    let data_type = def.data_type.to_string().to_lowercase();
+    if SERIAL_TYPES.contains(&data_type.as_str()) {
+        errors.push(LintError::new(ErrorCode::AddColumnSequenceDefault, format!(
+            "Column {} on table {} is added with type {}, which takes its default from a new sequence. Every existing row gets a different value, so the whole table is rewritten while holding an ACCESS EXCLUSIVE lock.", def.name, table, data_type).as_str()));
+    }
+    for opt in &def.options {
+        match &opt.option {
+            ast::ColumnOption::ForeignKey{foreign_table, ..} => errors.push(LintError::new(ErrorCode::InlineColumnForeignKey, format!(
+                "Column {} on table {} is added with a foreign key reference to {}. This validates it while holding locks on both tables; add the column first, then add the foreign key as NOT VALID and VALIDATE CONSTRAINT in a separate migration.", def.name, table, foreign_table).as_str())),
+            ast::ColumnOption::Unique{is_primary: true} => errors.push(LintError::new(ErrorCode::InlinePrimaryKeyColumn, format!(
+                "Column {} on table {} is added as its primary key. This builds a unique index and checks the column is not null while holding an ACCESS EXCLUSIVE lock; add the column first, backfill it, create a unique index on it CONCURRENTLY, then add the primary key with USING INDEX.", def.name, table).as_str())),
+            _ => (),
+        }
+    }
+    let complex = default.map_or(Vec::new(), |expr| lint_complex_default(table, &def.name, expr));
+    match default {
+        // The statement fails, so nothing else about the default matters.
+        _ if !complex.is_empty() => errors.extend(complex),
+        None if not_null => errors.push(LintError::new(ErrorCode::NotNullColumn, format!(
+            "Column {} on table {} is added with the NOT NULL option and no default. This fails if the table has any rows, and otherwise causes a full table rewrite.", def.name, table).as_str())),
+        // Before PostgreSQL 11 even a constant default is written to every row.
+        Some(expr) if version < PgVersion(11) && !expr_is_volatile(expr) => errors.push(LintError::new(ErrorCode::DefaultValue, format!(
+            "Column {} on table {} is added with a default value ({}). Before PostgreSQL 11 this is written to every existing row, causing a full table rewrite which can be very slow.", def.name, table, expr).as_str())),
+        // Since PostgreSQL 11 a constant default is stored in the catalog rather than written to
+        // every row, so this is the one safe way to add a NOT NULL column to an existing table.
+        Some(expr) if not_null && !expr_is_volatile(expr) => errors.push(LintError::new(ErrorCode::NotNullWithDefault, format!(
+            "Column {} on table {} is added with NOT NULL and a constant default ({}). This is a fast metadata-only change on PostgreSQL 11 and later, but rewrites the whole table on older versions.", def.name, table, expr).as_str())),
+        // Together these are the worst case, so they're reported as one finding rather than one
+        // for each of them.
+        Some(expr) if not_null => errors.push(LintError::new(ErrorCode::UnsafeNotNullDefault, format!(
+            "Column {} on table {} is added with NOT NULL and a volatile default ({}). The default has to be evaluated and written to every existing row, rewriting the whole table while holding an ACCESS EXCLUSIVE lock.", def.name, table, expr).as_str())),
+        Some(expr) if expr_is_volatile(expr) => {
+            if is_sequence_default(expr) {
+                errors.push(LintError::new(ErrorCode::AddColumnSequenceDefault, format!(
+                    "Column {} on table {} is added with a default from a sequence ({}). Every existing row gets a different value, so the whole table is rewritten while holding an ACCESS EXCLUSIVE lock.", def.name, table, expr).as_str()));
+            } else {
+                errors.push(LintError::new(ErrorCode::DefaultValue, format!(
+                    "Column {} on table {} is added with a volatile default value ({}). This has to be evaluated for every row, causing a full table rewrite which can be very slow.", def.name, table, expr).as_str()));
+            }
+        },
+        _ => (),
+    }
+    return errors;
+}
+
+/// Returns true if the given expression takes the next value from a sequence.
+fn is_sequence_default(expr: &ast::Expr) -> bool {
+    return match expr {
+        ast::Expr::Function(function) => function.name.to_string().rsplit('.').next().unwrap_or("").eq_ignore_ascii_case("nextval"),
+        ast::Expr::Nested(expr) | ast::Expr::Cast{expr, ..} => is_sequence_default(expr),
+        _ => false,
+    };
+}
+
+/// Keywords that look like identifiers but evaluate to the current time.
+const TIME_KEYWORDS: &[&str] = &["CURRENT_DATE", "CURRENT_TIME", "CURRENT_TIMESTAMP", "LOCALTIME", "LOCALTIMESTAMP"];
+
+/// Other keywords that look like identifiers but aren't column references.
+const SESSION_KEYWORDS: &[&str] = &["CURRENT_USER", "SESSION_USER", "USER", "CURRENT_ROLE", "CURRENT_CATALOG", "CURRENT_SCHEMA"];
+
+/// Returns true if the given expression might not evaluate to the same value every time.
+/// We don't know which functions are immutable, so we conservatively assume any call might not be.
+fn expr_is_volatile(expr: &ast::Expr) -> bool {
+    return expr_any(expr, &|expr| match expr {
+        ast::Expr::Function(_) | ast::Expr::Subquery(_) | ast::Expr::Exists(_) | ast::Expr::InSubquery{..} => true,
+        ast::Expr::Identifier(ident) => TIME_KEYWORDS.iter().any(|k| ident.to_string().eq_ignore_ascii_case(k)),
+        _ => false,
+    });
+}
+
+/// Returns true if the predicate is true for the given expression or any expression within it.
+/// Subqueries aren't looked inside.
+fn expr_any(expr: &ast::Expr, pred: &dyn Fn(&ast::Expr) -> bool) -> bool {
+    if pred(expr) {
+        return true;
+    }
+    return match expr {
+        ast::Expr::IsNull(expr) | ast::Expr::IsNotNull(expr) | ast::Expr::Nested(expr) => expr_any(expr, pred),
+        ast::Expr::UnaryOp{expr, ..} | ast::Expr::Cast{expr, ..} | ast::Expr::Collate{expr, ..} | ast::Expr::Extract{expr, ..} |
+        ast::Expr::InSubquery{expr, ..} => expr_any(expr, pred),
+        ast::Expr::BinaryOp{left, right, ..} => expr_any(left, pred) || expr_any(right, pred),
+        ast::Expr::Between{expr, low, high, ..} => [expr, low, high].iter().any(|e| expr_any(e, pred)),
+        ast::Expr::InList{expr, list, ..} => expr_any(expr, pred) || list.iter().any(|e| expr_any(e, pred)),
+        ast::Expr::Case{operand, conditions, results, else_result} =>
+            operand.iter().chain(else_result.iter()).any(|e| expr_any(e, pred)) ||
+            conditions.iter().chain(results.iter()).any(|e| expr_any(e, pred)),
+        ast::Expr::Function(function) => function.args.iter().any(|e| expr_any(e, pred)),
+        _ => false,
+    };
+}
+
+/// Returns a description of what a default expression contains that PostgreSQL doesn't allow in
+/// one, if anything: defaults can't contain subqueries or refer to other columns.
+fn complex_default(expr: &ast::Expr) -> Option<&'static str> {
+    let subquery = expr_any(expr, &|expr| match expr {
+        ast::Expr::Subquery(_) | ast::Expr::Exists(_) | ast::Expr::InSubquery{..} => true,
+        _ => false,
+    });
+    if subquery {
+        return Some("a subquery");
+    }
+    let column = expr_any(expr, &|expr| match expr {
+        ast::Expr::Identifier(ident) => !TIME_KEYWORDS.iter().chain(SESSION_KEYWORDS).any(|k| ident.to_string().eq_ignore_ascii_case(k)),
+        ast::Expr::CompoundIdentifier(_) => true,
+        _ => false,
+    });
+    return if column { Some("a column reference") } else { None };
+}
+
+/// Flags a default for the given column that contains a subquery or refers to a column.
+fn lint_complex_default(table: &ast::ObjectName, column: &ast::Ident, expr: &ast::Expr) -> Vec<LintError> {
+    return match complex_default(expr) {
+        Some(what) => vec![LintError::new(ErrorCode::ComplexDefault, format!(
+            "Column {} on table {} has a default containing {} ({}). PostgreSQL doesn't allow these in defaults, so the migration will fail; if the value depends on other data, backfill it instead, or compute it in a trigger.", column, table, what, expr).as_str())],
+        None => Vec::new(),
+    };
+}
+
+fn lint_add_constraint(table: &ast::ObjectName, constraint: &ast::TableConstraint) -> Vec<LintError> {
+    return match constraint {
+        ast::TableConstraint::ForeignKey{foreign_table, not_valid: false, ..} =>
+            vec![LintError::new(ErrorCode::ForeignKeyNotValidated, format!(
+                "Foreign key from {} to {} is added without NOT VALID. This validates every existing row while holding locks on both tables; add it as NOT VALID and then VALIDATE CONSTRAINT in a separate migration.", table, foreign_table).as_str())],
+        ast::TableConstraint::Check{not_valid: false, ..} =>
+            vec![LintError::new(ErrorCode::CheckConstraintNotValidated, format!(
+                "CHECK constraint on {} is added without NOT VALID. This scans the entire table to validate it; add it as NOT VALID and then VALIDATE CONSTRAINT in a separate migration.", table).as_str())],
+        ast::TableConstraint::Unique{is_primary: true, using_index: None, ..} =>
+            vec![LintError::new(ErrorCode::AddPrimaryKey, format!(
+                "Primary key is added to {}. This builds a unique index and sets its columns NOT NULL while holding an exclusive lock; instead create a unique index CONCURRENTLY first, then add the constraint with USING INDEX.", table).as_str())],
+        ast::TableConstraint::Unique{is_primary: false, using_index: None, ..} =>
+            vec![LintError::new(ErrorCode::AddUniqueConstraint, format!(
+                "UNIQUE constraint is added to {}. This builds a unique index while holding an exclusive lock; instead create a unique index CONCURRENTLY first, then add the constraint with USING INDEX.", table).as_str())],
+        _ => Vec::new(),
+    };
+}
+
+fn lint_alter_column(table: &ast::ObjectName, name: &ast::Ident, op: &ast::AlterColumnOperation) -> Vec<LintError> {
+    return match op {
+        ast::AlterColumnOperation::SetDataType{data_type} =>
+            vec![LintError::new(ErrorCode::ColumnTypeChange, format!(
+                "Column {} on table {} is changed to type {}. This usually causes a full table rewrite while holding an exclusive lock; the exceptions are a few binary-compatible changes such as increasing the length of a VARCHAR or changing VARCHAR to TEXT.", name, table, data_type).as_str())],
+        ast::AlterColumnOperation::SetNotNull =>
+            vec![LintError::new(ErrorCode::SetNotNull, format!(
+                "Column {} on table {} is set to NOT NULL. This requires a full table scan while holding an exclusive lock; instead add a CHECK ({} IS NOT NULL) constraint as NOT VALID and then validate it separately.", name, table, name).as_str())],
+        ast::AlterColumnOperation::DropNotNull =>
+            vec![LintError::new(ErrorCode::DropNotNull, format!(
+                "Column {} on table {} has its NOT NULL constraint dropped. This is cheap, but application code may rely on it never being null, so make sure that's intended.", name, table).as_str())],
+        ast::AlterColumnOperation::SetDefault{value} if complex_default(value).is_some() =>
+            lint_complex_default(table, name, value),
+        ast::AlterColumnOperation::SetDefault{value} if expr_is_volatile(value) =>
+            vec![LintError::new(ErrorCode::VolatileSetDefault, format!(
+                "Column {} on table {} has its default set to a volatile value ({}). This doesn't rewrite the table, but only applies to new rows, so existing rows still need backfilling before the column can be made NOT NULL.", name, table, value).as_str())],
+        _ => Vec::new(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::{lint_errors, lint_str, Config, Severity};
+
+    #[test]
+    fn test_create_table() {
+        let errors = lint_errors(&PathBuf::from("test_data/create_table.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_create_table_with_defaults() {
+        let errors = lint_errors(&PathBuf::from("test_data/create_table_with_defaults.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_add_column_without_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_without_default.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_add_column_with_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_with_default.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_add_column_with_volatile_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_with_volatile_default.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::DefaultValue, "").at(1, 35)], errors);
+    }
+
+    #[test]
+    fn test_lint_rename_table() {
+        let errors = lint_errors(&PathBuf::from("test_data/rename_table.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::RenameTable, "").at(1, 1)], errors);
+        assert_eq!(Severity::Info, errors[0].severity());
+    }
+
+    #[test]
+    fn test_lint_rename_column_in_place() {
+        let errors = lint_errors(&PathBuf::from("test_data/rename_column.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::RenameColumn, "").at(1, 38)], errors);
+    }
+
+    #[test]
+    fn test_lint_drop_column() {
+        let errors = lint_errors(&PathBuf::from("test_data/drop_column.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::DropColumn, "").at(1, 36)], errors);
+    }
+
+    #[test]
+    fn test_lint_alter_column_type() {
+        let errors = lint_errors(&PathBuf::from("test_data/alter_column_type.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::ColumnTypeChange, "").at(1, 37)], errors);
+    }
+
+    #[test]
+    fn test_lint_drop_not_null() {
+        let errors = lint_errors(&PathBuf::from("test_data/drop_not_null.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::DropNotNull, "").at(1, 37)], errors);
+        assert_eq!(Severity::Info, errors[0].severity());
+    }
+
+    #[test]
+    fn test_lint_complex_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/complex_default.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::ComplexDefault, "").at(1, 35),
+            LintError::new(ErrorCode::ComplexDefault, "").at(2, 37),
+        ], errors);
+        assert!(errors[0].message().contains("containing a subquery"));
+        assert!(errors[1].message().contains("containing a column reference"));
+    }
+
+    #[test]
+    fn test_lint_literal_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/literal_default.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+        assert_eq!(0, lint_str("ALTER TABLE characters ADD COLUMN created_by VARCHAR(50) DEFAULT CURRENT_USER;").len());
+    }
+
+    #[test]
+    fn test_lint_alter_column_set_volatile_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/alter_column_set_volatile_default.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::VolatileSetDefault, "").at(1, 37)], errors);
+        assert_eq!(Severity::Info, errors[0].severity());
+    }
+
+    #[test]
+    fn test_lint_alter_column_set_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/alter_column_set_default.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_alter_column_drop_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/alter_column_drop_default.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_set_not_null() {
+        let errors = lint_errors(&PathBuf::from("test_data/set_not_null.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::SetNotNull, "").at(1, 37)], errors);
+    }
+
+    #[test]
+    fn test_lint_not_null_check_constraint() {
+        let errors = lint_errors(&PathBuf::from("test_data/not_null_check_constraint.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_add_foreign_key() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_foreign_key.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::ForeignKeyNotValidated, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_add_foreign_key_not_valid() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_foreign_key_not_valid.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_add_check_constraint() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_check_constraint.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::CheckConstraintNotValidated, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_add_check_constraint_not_valid() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_check_constraint_not_valid.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_add_column_not_null() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_not_null.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::NotNullColumn, "").at(1, 35)], errors);
+    }
+
+    #[test]
+    fn test_lint_add_column_with_default_before_pg11() {
+        let config = Config::parse(r#"pg_version = "10""#).unwrap();
+        for file in &["test_data/add_column_with_default.sql", "test_data/add_column_not_null_with_default.sql"] {
+            let errors = lint_errors(&PathBuf::from(file), &config);
+            assert_eq!(vec![LintError::new(ErrorCode::DefaultValue, "").at(1, 35)], errors, "{}", file);
+        }
+        let config = Config::parse(r#"pg_version = "11""#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_with_default.sql"), &config);
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_add_column_not_null_with_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_not_null_with_default.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::NotNullWithDefault, "").at(1, 35)], errors);
+        assert_eq!(Severity::Info, errors[0].severity());
+    }
+
+    #[test]
+    fn test_lint_add_column_not_null_with_volatile_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_not_null_with_volatile_default.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::UnsafeNotNullDefault, "").at(1, 35)], errors);
+    }
+
+    #[test]
+    fn test_lint_unsafe_not_null_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/unsafe_not_null_default.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::UnsafeNotNullDefault, "").at(1, 35),
+            LintError::new(ErrorCode::DefaultValue, "").at(2, 35),
+            LintError::new(ErrorCode::NotNullColumn, "").at(3, 35),
+            LintError::new(ErrorCode::NotNullWithDefault, "").at(4, 35),
+        ], errors);
+        assert_eq!(Severity::Error, errors[0].severity());
+    }
+
+    #[test]
+    fn test_lint_serial_column() {
+        let config = Config::parse(r#"enabled = ["PreferIdentity"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/serial_column.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::PreferIdentity, "").at(2, 4),
+            LintError::new(ErrorCode::PreferIdentity, "").at(5, 35),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_identity_column() {
+        let config = Config::parse(r#"enabled = ["PreferIdentity"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/identity_column.sql"), &config);
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_unbounded_varchar() {
+        let config = Config::parse(r#"enabled = ["UnboundedVarchar"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/unbounded_varchar.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::UnboundedVarchar, "").at(3, 4),
+            LintError::new(ErrorCode::UnboundedVarchar, "").at(5, 4),
+            LintError::new(ErrorCode::UnboundedVarchar, "").at(7, 35),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_add_primary_key() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_primary_key.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::AddPrimaryKey, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_add_unique_constraint() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_unique_constraint.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::AddUniqueConstraint, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_add_unique_constraint_using_index() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_unique_constraint_using_index.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_inline_primary_key_column() {
+        let errors = lint_errors(&PathBuf::from("test_data/inline_primary_key_column.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::InlinePrimaryKeyColumn, "").at(2, 35)], errors);
+    }
+
+    #[test]
+    fn test_lint_inline_column_foreign_key() {
+        let errors = lint_errors(&PathBuf::from("test_data/inline_column_foreign_key.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::InlineColumnForeignKey, "").at(1, 35)], errors);
+    }
+
+    #[test]
+    fn test_lint_add_column_sequence_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_column_sequence_default.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::AddColumnSequenceDefault, "").at(1, 35),
+            LintError::new(ErrorCode::UnsafeNotNullDefault, "").at(2, 35),
+            LintError::new(ErrorCode::AddColumnSequenceDefault, "").at(3, 35),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_add_primary_key_using_index() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_primary_key_using_index.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_add_column_message_mentions_table() {
+        let errors = lint_str("ALTER TABLE characters ADD COLUMN created TIMESTAMP DEFAULT now();");
+        assert!(errors[0].message().starts_with("Column created on table characters is added with a volatile default value"));
+    }
+}
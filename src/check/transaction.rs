@@ -0,0 +1,162 @@
+//! Rules for statements that depend on the transaction they run in, and the locks they wait for.
+use sqlparser::ast;
+use crate::{index_tokens, rules, ErrorCode, LintError};
+use super::write::takes_lock;
+use super::{CheckContext, Rule};
+
+/// Checks statements that depend on whether they're inside a transaction block.
+pub(crate) struct TransactionCheck;
+
+impl Rule for TransactionCheck {
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return lint_transaction(stmt, &mut context.file.in_transaction);
+    }
+}
+
+/// Checks that lock_timeout is set before anything takes a lock.
+pub(crate) struct LockTimeoutCheck;
+
+impl Rule for LockTimeoutCheck {
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return lint_lock_timeout(stmt, &mut context.file.lock_timeout);
+    }
+}
+
+/// Checks for statements that can't run inside a transaction block.
+pub(crate) struct TransactionIncompatibleCheck;
+
+impl Rule for TransactionIncompatibleCheck {
+    fn check(&self, _: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return Vec::new();
+    }
+
+    fn check_sql(&self, sql: &str, _: &mut CheckContext) -> Vec<LintError> {
+        return lint_transaction_incompatible(sql);
+    }
+}
+
+/// Lints a statement for problems that depend on whether it's inside a transaction block.
+/// Updates in_transaction as transactions are started and finished.
+fn lint_transaction(stmt: &ast::Statement, in_transaction: &mut bool) -> Vec<LintError> {
+    return match stmt {
+        ast::Statement::StartTransaction{..} => {
+            *in_transaction = true;
+            Vec::new()
+        },
+        ast::Statement::Commit{..} | ast::Statement::Rollback{..} => {
+            *in_transaction = false;
+            Vec::new()
+        },
+        ast::Statement::CreateIndex{name, concurrently: true, ..} if *in_transaction =>
+            vec![LintError::new(ErrorCode::ConcurrentIndexInTransaction, format!(
+                "Index {} is created CONCURRENTLY inside a transaction block. This is not allowed and will fail when the migration is run.", name).as_str())],
+        _ => Vec::new(),
+    };
+}
+
+/// Tracks whether a file has set lock_timeout yet, or whether we've already complained that it hasn't.
+#[derive(Debug, PartialEq)]
+pub(crate) enum LockTimeout {
+    Unset,
+    Set,
+    Reported,
+}
+
+/// Lints a statement for taking locks before lock_timeout has been set. This is only reported
+/// once per file, at the first statement that needs it.
+fn lint_lock_timeout(stmt: &ast::Statement, state: &mut LockTimeout) -> Vec<LintError> {
+    return match stmt {
+        ast::Statement::SetVariable{variable, ..} if variable.to_string().eq_ignore_ascii_case("lock_timeout") => {
+            *state = LockTimeout::Set;
+            Vec::new()
+        },
+        _ if *state == LockTimeout::Unset && takes_lock(stmt) => {
+            *state = LockTimeout::Reported;
+            vec![LintError::new(ErrorCode::MissingLockTimeout,
+                "This statement takes a lock but lock_timeout hasn't been set. If the lock can't be acquired immediately it will queue behind other queries and block everything else on the table; SET lock_timeout at the start of the migration to fail fast instead.")]
+        },
+        _ => Vec::new(),
+    };
+}
+
+/// Flags a statement that can't run inside a transaction block, which fails if the migration
+/// tool runs each file in one. Works on the SQL so it covers statements sqlparser can't parse;
+/// comments and string literals are skipped so the keywords aren't matched inside them.
+fn lint_transaction_incompatible(sql: &str) -> Vec<LintError> {
+    let words = index_tokens(sql).iter().map(|t| t.to_uppercase()).collect::<Vec<_>>();
+    let words = words.iter().map(|w| w.as_str()).collect::<Vec<_>>();
+    return match rules::transaction_incompatible(&words) {
+        Some(statement) => vec![LintError::new(ErrorCode::TransactionIncompatible, format!(
+            "{} can't run inside a transaction block. If the migration tool runs this file in a transaction, it will fail.", statement.name).as_str())],
+        None => Vec::new(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::{lint_errors, Config};
+
+    #[test]
+    fn test_lint_create_index_async_in_transaction() {
+        let errors = lint_errors(&PathBuf::from("test_data/create_index_async_in_transaction.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::ConcurrentIndexInTransaction, "").at(2, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_create_index_async_after_transaction() {
+        let errors = lint_errors(&PathBuf::from("test_data/create_index_async_after_transaction.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_missing_lock_timeout() {
+        let config = Config::parse(r#"enabled = ["MissingLockTimeout"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/missing_lock_timeout.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::MissingLockTimeout, "").at(2, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_lock_timeout() {
+        let config = Config::parse(r#"enabled = ["MissingLockTimeout"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/lock_timeout.sql"), &config);
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_transaction_incompatible() {
+        let config = Config::parse(r#"enabled = ["TransactionIncompatible"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/transaction_incompatible.sql"), &config)
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::TransactionIncompatible)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![
+            LintError::new(ErrorCode::TransactionIncompatible, "").at(1, 1),
+            LintError::new(ErrorCode::TransactionIncompatible, "").at(2, 1),
+            LintError::new(ErrorCode::TransactionIncompatible, "").at(4, 1),
+            LintError::new(ErrorCode::TransactionIncompatible, "").at(5, 1),
+        ], errors);
+        assert!(errors[2].message().starts_with("VACUUM can't run inside a transaction block."));
+        assert!(errors[3].message().starts_with("ALTER TYPE ... ADD VALUE can't run"));
+    }
+
+    #[test]
+    fn test_lint_transaction_incompatible_ignores_comments_and_strings() {
+        assert_eq!(Vec::<LintError>::new(), lint_transaction_incompatible(
+            "CREATE TABLE notes (body TEXT DEFAULT 'create index concurrently') /* index concurrently */"));
+        assert_eq!(Vec::<LintError>::new(), lint_transaction_incompatible("REINDEX TABLE characters -- not CONCURRENTLY\n"));
+        assert_eq!(Vec::<LintError>::new(), lint_transaction_incompatible("CREATE INDEX \"concurrently idx\" ON characters (name)"));
+        assert_eq!(Vec::<LintError>::new(), lint_transaction_incompatible("CREATE FUNCTION f() RETURNS void AS $$ CREATE INDEX CONCURRENTLY a ON b (c) $$"));
+        assert_eq!(vec![LintError::new(ErrorCode::TransactionIncompatible, "")],
+                   lint_transaction_incompatible("CREATE UNIQUE INDEX CONCURRENTLY name_idx ON characters (name)"));
+    }
+
+    #[test]
+    fn test_lint_transaction_incompatible_disabled_by_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/transaction_incompatible.sql"), &Config::default());
+        assert!(errors.iter().all(|e| e.code != ErrorCode::TransactionIncompatible));
+    }
+}
@@ -0,0 +1,248 @@
+//! Rules for statements sqlparser can't parse, which are recognised from their SQL instead.
+use sqlparser::ast;
+use crate::{tokens, words, Config, ErrorCode, LintError, PgVersion};
+use super::index::{check_index_method, is_concurrent_index, is_create_index, is_unnamed_index, unnamed_index};
+use super::{CheckContext, Rule};
+
+/// Checks the statements that matter to us that sqlparser doesn't support, from their SQL.
+pub(crate) struct UnparsedCheck;
+
+impl Rule for UnparsedCheck {
+    fn check(&self, _: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return Vec::new();
+    }
+
+    fn check_unparsed(&self, sql: &str, context: &mut CheckContext) -> Option<Vec<LintError>> {
+        return lint_unparsed(sql, context.config);
+    }
+}
+
+/// Lints a statement that sqlparser couldn't parse. Some statements that matter to us aren't
+/// supported by the parser, so we recognise them from their raw text instead.
+/// Returns None if it isn't one of those statements, in which case it's a genuine syntax error.
+fn lint_unparsed(sql: &str, config: &Config) -> Option<Vec<LintError>> {
+    let words = words(sql);
+    let words = words.iter().map(|w| w.as_str()).collect::<Vec<_>>();
+    return match words.as_slice() {
+        ["CLUSTER", ..] => Some(vec![LintError::new(ErrorCode::HeavyMaintenance,
+            "CLUSTER rewrites the entire table while holding an ACCESS EXCLUSIVE lock on it, blocking all reads and writes. It has no place in a zero-downtime migration.")]),
+        ["VACUUM", rest @ ..] if rest.contains(&"FULL") => Some(vec![LintError::new(ErrorCode::HeavyMaintenance,
+            "VACUUM FULL rewrites the entire table while holding an ACCESS EXCLUSIVE lock on it, blocking all reads and writes. It has no place in a zero-downtime migration.")]),
+        ["REINDEX", rest @ ..] if !rest.contains(&"CONCURRENTLY") => Some(vec![LintError::new(ErrorCode::HeavyMaintenance,
+            if config.pg_version() < PgVersion(12) {
+                "REINDEX blocks writes to the table while the index is rebuilt. REINDEX CONCURRENTLY needs PostgreSQL 12, so create a new index CONCURRENTLY and drop the old one instead."
+            } else {
+                "REINDEX without CONCURRENTLY blocks writes to the table while the index is rebuilt. Use REINDEX ... CONCURRENTLY instead."
+            })]),
+        // sqlparser doesn't know about tablespaces at all.
+        ["ALTER", object @ "TABLE", rest @ ..] | ["ALTER", object @ "INDEX", rest @ ..] |
+        ["ALTER", "MATERIALIZED", object @ "VIEW", rest @ ..] if rest.windows(2).any(|w| w == ["SET", "TABLESPACE"]) =>
+            Some(vec![LintError::new(ErrorCode::SetTablespace, format!(
+                "{} is moved to another tablespace. This rewrites all of its data while holding an ACCESS EXCLUSIVE lock, blocking all reads and writes; consider pg_repack, or copying the data to a new table with logical replication, instead.",
+                match *object {
+                    "TABLE" => "A table",
+                    "INDEX" => "An index",
+                    _ => "A materialized view",
+                }).as_str())]),
+        // Nor does it know about disabling triggers or rules.
+        ["ALTER", "TABLE", rest @ ..] if rest.windows(2).any(|w| w == ["DISABLE", "TRIGGER"] || w == ["DISABLE", "RULE"]) =>
+            Some(vec![LintError::new(ErrorCode::DisabledIntegrity,
+                if rest.windows(3).any(|w| w == ["DISABLE", "TRIGGER", "ALL"]) {
+                    "All triggers on a table are disabled, including the ones enforcing foreign keys, so nothing checks rows written in the meantime. Make sure they're re-enabled with ENABLE TRIGGER ALL, even if the migration fails partway."
+                } else {
+                    "A trigger or rule on a table is disabled, so it's skipped for rows written in the meantime. Make sure it's re-enabled, even if the migration fails partway."
+                })]),
+        ["ALTER", "TABLE", rest @ ..] if rest.windows(2).any(|w| w == ["ENABLE", "TRIGGER"] || w == ["ENABLE", "RULE"]) => Some(Vec::new()),
+        // Nor REFRESH MATERIALIZED VIEW.
+        ["REFRESH", "MATERIALIZED", "VIEW", rest @ ..] =>
+            Some(lint_refresh_materialized_view(&view_name(sql), rest.first() == Some(&"CONCURRENTLY"))),
+        ["ALTER", "TABLE", rest @ ..] if rest.contains(&"ADD") && rest.contains(&"STORED") &&
+            rest.windows(2).any(|w| w == ["GENERATED", "ALWAYS"]) => Some(vec![LintError::new(ErrorCode::AddGeneratedColumn,
+            "A stored generated column is added to an existing table. Its value has to be computed for every row, causing a full table rewrite while holding an ACCESS EXCLUSIVE lock.")]),
+        // sqlparser requires indexes to be named and doesn't support USING, so those end up here.
+        ["CREATE", rest @ ..] if is_unnamed_index(rest) || (is_create_index(rest) && rest.contains(&"USING")) => {
+            let mut errors = if is_unnamed_index(rest) { vec![unnamed_index(None)] } else { Vec::new() };
+            errors.extend(check_index_method(sql, config));
+            if is_concurrent_index(rest) {
+                errors.push(LintError::new(ErrorCode::ConcurrentIndex,
+                    "An index is created CONCURRENTLY, which doesn't block writes to the table."));
+            } else {
+                errors.push(LintError::new(ErrorCode::NonConcurrentIndex,
+                    "An index is created without CONCURRENTLY. This requires holding an exclusive table lock while the index is built, which can cause downtime."));
+            }
+            Some(errors)
+        },
+        // Nor does it support partitioning. Creating the table is fine; it's recorded by FileLinter.
+        ["CREATE", rest @ ..] if is_create_partitioned_table(rest) => Some(Vec::new()),
+        // sqlparser doesn't support CREATE TABLE ... AS either. WITH NO DATA only creates the table.
+        ["CREATE", rest @ ..] if is_create_table_as(rest) => Some(if rest.ends_with(&["WITH", "NO", "DATA"]) {
+            Vec::new()
+        } else {
+            vec![LintError::new(ErrorCode::CreateTableAsSelect,
+                "A table is created from the results of a query. The whole query runs and its results are written within the migration, which can take a long time for large source tables, and they stay locked against DDL until it commits; consider creating the table empty and filling it in batches instead.")]
+        }),
+        ["VACUUM", ..] | ["REINDEX", ..] => Some(Vec::new()),
+        _ => None,
+    };
+}
+
+/// Returns true if the given words (following CREATE) create a table from the results of a query.
+fn is_create_table_as(words: &[&str]) -> bool {
+    let words = match words {
+        ["TEMP", rest @ ..] | ["TEMPORARY", rest @ ..] | ["UNLOGGED", rest @ ..] => rest,
+        _ => words,
+    };
+    return match words {
+        ["TABLE", rest @ ..] => rest.windows(2).any(|w| match w {
+            ["AS", "SELECT"] | ["AS", "WITH"] | ["AS", "TABLE"] | ["AS", "VALUES"] => true,
+            _ => false,
+        }),
+        _ => false,
+    };
+}
+
+/// Returns true if the given words (following CREATE) create a partitioned table.
+pub(crate) fn is_create_partitioned_table(words: &[&str]) -> bool {
+    let words = match words {
+        ["UNLOGGED", rest @ ..] => rest,
+        _ => words,
+    };
+    return match words {
+        ["TABLE", rest @ ..] => rest.windows(2).any(|w| w == ["PARTITION", "BY"]),
+        _ => false,
+    };
+}
+
+/// Returns the name of the view refreshed by a REFRESH MATERIALIZED VIEW statement, as written.
+fn view_name(sql: &str) -> String {
+    let tokens = tokens(sql);
+    let mut tokens = tokens.iter()
+        .skip_while(|token| !token.text.eq_ignore_ascii_case("VIEW"))
+        .skip(1)
+        .skip_while(|token| token.text.eq_ignore_ascii_case("CONCURRENTLY"))
+        .peekable();
+    // The name is one or more (possibly quoted) identifiers separated by dots.
+    let mut parts = Vec::new();
+    while let Some(token) = tokens.next() {
+        if !token.is_word() {
+            break;
+        }
+        parts.push(token.text.as_str());
+        if !tokens.peek().map_or(false, |token| token.text == ".") {
+            break;
+        }
+        tokens.next();
+    }
+    return parts.join(".");
+}
+
+/// Flags a materialized view refresh that blocks reads from it.
+fn lint_refresh_materialized_view(name: &str, concurrently: bool) -> Vec<LintError> {
+    if concurrently {
+        return Vec::new();
+    }
+    return vec![LintError::new(ErrorCode::NonConcurrentRefresh, format!(
+        "Materialized view {} is refreshed without CONCURRENTLY. This holds an exclusive lock on it for the whole refresh, blocking all reads of it. Use REFRESH MATERIALIZED VIEW CONCURRENTLY instead, which needs a unique index on the view.", name).as_str())];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::lint_errors;
+
+    #[test]
+    fn test_lint_add_generated_column() {
+        let errors = lint_errors(&PathBuf::from("test_data/add_generated_column.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::AddGeneratedColumn, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_cluster() {
+        let errors = lint_errors(&PathBuf::from("test_data/cluster.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::HeavyMaintenance, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_vacuum_full() {
+        let errors = lint_errors(&PathBuf::from("test_data/vacuum_full.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::HeavyMaintenance, "").at(1, 1),
+            LintError::new(ErrorCode::HeavyMaintenance, "").at(2, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_vacuum() {
+        let errors = lint_errors(&PathBuf::from("test_data/vacuum.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_reindex() {
+        let errors = lint_errors(&PathBuf::from("test_data/reindex.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::HeavyMaintenance, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_reindex_concurrently() {
+        let errors = lint_errors(&PathBuf::from("test_data/reindex_concurrently.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_refresh_materialized_view_sync() {
+        let errors = lint_errors(&PathBuf::from("test_data/refresh_materialized_view_sync.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentRefresh, "").at(1, 1)], errors);
+        assert!(errors[0].message().starts_with("Materialized view character_stats is refreshed without CONCURRENTLY."));
+    }
+
+    #[test]
+    fn test_view_name() {
+        assert_eq!("character_stats", view_name("REFRESH MATERIALIZED VIEW character_stats;"));
+        assert_eq!("stats.characters", view_name("REFRESH MATERIALIZED VIEW CONCURRENTLY stats . characters WITH DATA"));
+        assert_eq!("\"Character Stats\"", view_name("REFRESH MATERIALIZED VIEW \"Character Stats\""));
+    }
+
+    #[test]
+    fn test_lint_refresh_materialized_view_async() {
+        let errors = lint_errors(&PathBuf::from("test_data/refresh_materialized_view_async.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_disabled_integrity() {
+        let errors = lint_errors(&PathBuf::from("test_data/disabled_integrity.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DisabledIntegrity, "").at(1, 1),
+            LintError::new(ErrorCode::DisabledIntegrity, "").at(3, 1),
+            LintError::new(ErrorCode::DisabledIntegrity, "").at(4, 1),
+        ], errors);
+        assert!(errors[0].message().contains("foreign keys"));
+        assert!(!errors[1].message().contains("foreign keys"));
+    }
+
+    #[test]
+    fn test_lint_create_table_as_select() {
+        let config = Config::parse(r#"enabled = ["CreateTableAsSelect"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/create_table_as_select.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::CreateTableAsSelect, "").at(1, 1),
+            LintError::new(ErrorCode::CreateTableAsSelect, "").at(2, 1),
+        ], errors);
+        let errors = lint_errors(&PathBuf::from("test_data/create_table.sql"), &config);
+        assert_eq!(0, errors.len());
+        let errors = lint_errors(&PathBuf::from("test_data/create_table_as_select.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_set_tablespace() {
+        let errors = lint_errors(&PathBuf::from("test_data/set_tablespace.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::SetTablespace, "").at(1, 1),
+            LintError::new(ErrorCode::SetTablespace, "").at(2, 1),
+        ], errors);
+        assert!(errors[1].message().starts_with("An index is moved to another tablespace."));
+    }
+}
@@ -0,0 +1,240 @@
+//! Rules for writes to existing rows, and the locks taken around them.
+use sqlparser::ast;
+use crate::{index_tokens, ErrorCode, LintError};
+use super::{CheckContext, Rule};
+
+/// Checks writes to existing rows for touching too many of them at once.
+pub(crate) struct WriteCheck;
+
+impl Rule for WriteCheck {
+    fn check(&self, stmt: &ast::Statement, _: &mut CheckContext) -> Vec<LintError> {
+        return match stmt {
+            ast::Statement::Update{table_name, selection: None, ..} => vec![unbounded_write("UPDATE", table_name)],
+            ast::Statement::Delete{table_name, selection: None} => vec![unbounded_write("DELETE", table_name)],
+            ast::Statement::Insert{table_name, source, ..} => lint_insert(table_name, source).into_iter().collect(),
+            _ => Vec::new(),
+        };
+    }
+}
+
+/// Checks for table locks taken after a long-running write earlier in the file.
+pub(crate) struct LongWriteCheck;
+
+impl Rule for LongWriteCheck {
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return lint_lock_after_long_write(stmt, &mut context.file.long_write);
+    }
+}
+
+/// Checks for bulk loads into tables locked earlier in the file.
+pub(crate) struct LoadAfterLockCheck;
+
+impl Rule for LoadAfterLockCheck {
+    fn check(&self, stmt: &ast::Statement, context: &mut CheckContext) -> Vec<LintError> {
+        return lint_load_after_lock(stmt, &mut context.file.locked_tables);
+    }
+
+    /// sqlparser can't parse most forms of COPY, so it's recognised from the SQL.
+    fn check_sql(&self, sql: &str, context: &mut CheckContext) -> Vec<LintError> {
+        return lint_copy_after_lock(sql, &context.file.locked_tables);
+    }
+}
+
+fn unbounded_write(operation: &str, table: &ast::ObjectName) -> LintError {
+    return LintError::new(ErrorCode::UnboundedWrite, format!(
+        "{} on {} has no WHERE clause. This locks and rewrites every row in the table at once; consider updating it in batches instead.", operation, table).as_str());
+}
+
+/// Lints a statement for taking a table lock after a long-running write earlier in the file,
+/// which it may have to wait behind. This is reported once for each such write.
+fn lint_lock_after_long_write(stmt: &ast::Statement, long_write: &mut Option<String>) -> Vec<LintError> {
+    if let Some(write) = long_write_description(stmt) {
+        *long_write = Some(write);
+        return Vec::new();
+    }
+    if !takes_lock(stmt) {
+        return Vec::new();
+    }
+    return match long_write.take() {
+        Some(write) => vec![LintError::new(ErrorCode::LockAfterLongWrite, format!(
+            "This statement takes a table lock after {} earlier in the file. If they run in the same transaction it will wait behind the write's row locks, queueing everything else on the table behind it; consider splitting them into separate migrations.", write).as_str())],
+        None => Vec::new(),
+    };
+}
+
+/// Returns a description of the given statement if it's a write that can run for a long time,
+/// i.e. one that isn't limited to some of the rows.
+fn long_write_description(stmt: &ast::Statement) -> Option<String> {
+    return match stmt {
+        ast::Statement::Update{table_name, selection: None, ..} => Some(format!("an UPDATE of every row in {}", table_name)),
+        ast::Statement::Delete{table_name, selection: None} => Some(format!("a DELETE of every row in {}", table_name)),
+        ast::Statement::Insert{table_name, source, ..} => lint_insert(table_name, source).map(|_| format!("an unbatched INSERT into {}", table_name)),
+        _ => None,
+    };
+}
+
+/// Returns the table that the given statement takes an exclusive lock on, along with a
+/// description of the statement, if it does.
+fn lock_description(stmt: &ast::Statement) -> Option<(&ast::ObjectName, String)> {
+    return match stmt {
+        ast::Statement::AlterTable{name, ..} => Some((name, format!("an ALTER TABLE of {}", name))),
+        ast::Statement::CreateIndex{table_name, concurrently: false, ..} =>
+            Some((table_name, format!("a CREATE INDEX on {} without CONCURRENTLY", table_name))),
+        ast::Statement::Truncate{table_name, ..} => Some((table_name, format!("a TRUNCATE of {}", table_name))),
+        _ => None,
+    };
+}
+
+/// Returns true if the given statement takes a lock that blocks other queries on a table.
+pub(crate) fn takes_lock(stmt: &ast::Statement) -> bool {
+    return match stmt {
+        ast::Statement::AlterTable{..} | ast::Statement::Truncate{..} => true,
+        ast::Statement::CreateIndex{concurrently, ..} => !*concurrently,
+        ast::Statement::Drop{object_type: ast::ObjectType::Index, concurrently, ..} => !*concurrently,
+        ast::Statement::Drop{object_type: ast::ObjectType::Table, ..} => true,
+        _ => false,
+    };
+}
+
+/// Lints an INSERT statement. Inserting literal VALUES is fine, but copying the results of a
+/// query with no LIMIT can insert an unbounded number of rows in a single transaction.
+fn lint_insert(table: &ast::ObjectName, source: &ast::Query) -> Option<LintError> {
+    return match &source.body {
+        ast::SetExpr::Values(_) => None,
+        _ if source.limit.is_some() || source.fetch.is_some() => None,
+        _ => Some(LintError::new(ErrorCode::UnbatchedBackfill, format!(
+            "INSERT into {} copies every row of a query with no LIMIT in one transaction. On a large table this can run for a long time and generate a lot of WAL; consider inserting in batches instead.", table).as_str())),
+    };
+}
+
+/// Lints an INSERT ... SELECT into a table that an earlier statement in the file locked, keeping
+/// track of the tables locked since the last COMMIT along with a description of what locked them.
+/// COPY is handled by lint_copy_after_lock, since sqlparser can't parse most forms of it.
+fn lint_load_after_lock(stmt: &ast::Statement, locked_tables: &mut Vec<(String, String)>) -> Vec<LintError> {
+    if let Some((table, description)) = lock_description(stmt) {
+        locked_tables.push((table.to_string().to_lowercase(), description));
+        return Vec::new();
+    }
+    return match stmt {
+        ast::Statement::Commit{..} | ast::Statement::Rollback{..} => {
+            locked_tables.clear();
+            Vec::new()
+        },
+        ast::Statement::Insert{table_name, source, ..} => match &source.body {
+            ast::SetExpr::Values(_) => Vec::new(),
+            _ => load_after_lock("INSERT", &table_name.to_string(), locked_tables),
+        },
+        _ => Vec::new(),
+    };
+}
+
+/// Lints a COPY ... FROM into a table that an earlier statement in the file locked.
+fn lint_copy_after_lock(sql: &str, locked_tables: &[(String, String)]) -> Vec<LintError> {
+    let tokens = index_tokens(sql);
+    if !tokens.first().map_or(false, |t| t.eq_ignore_ascii_case("COPY")) || !tokens.iter().any(|t| t.eq_ignore_ascii_case("FROM")) {
+        return Vec::new();
+    }
+    let table = tokens[1..].iter()
+        .take_while(|t| *t != "(" && !t.eq_ignore_ascii_case("FROM"))
+        .map(|t| t.as_str())
+        .collect::<Vec<_>>()
+        .join(".");
+    return load_after_lock("COPY", &table, locked_tables);
+}
+
+/// Returns an error for a bulk load into the given table if an earlier statement locked it.
+/// If they're in the same transaction, the lock is held for the whole of the load.
+fn load_after_lock(kind: &str, table: &str, locked_tables: &[(String, String)]) -> Vec<LintError> {
+    let key = table.to_lowercase();
+    return match locked_tables.iter().find(|(locked, _)| *locked == key) {
+        Some((_, description)) => vec![LintError::new(ErrorCode::LoadAfterLock, format!(
+            "This {} bulk loads data into {} after {} earlier in the file. If they run in the same transaction the table stays locked until the load finishes; consider loading it in a later migration, once the DDL has committed.",
+            kind, table, description).as_str())],
+        None => Vec::new(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::{lint_errors, Config};
+
+    #[test]
+    fn test_lint_update_without_where() {
+        let errors = lint_errors(&PathBuf::from("test_data/update_without_where.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::UnboundedWrite, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_delete_without_where() {
+        let errors = lint_errors(&PathBuf::from("test_data/delete_without_where.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::UnboundedWrite, "").at(1, 1)], errors);
+    }
+
+    #[test]
+    fn test_lint_writes_with_where() {
+        let errors = lint_errors(&PathBuf::from("test_data/writes_with_where.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_unbatched_backfill() {
+        let config = Config::parse(r#"enabled = ["UnbatchedBackfill"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/insert_select.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::UnbatchedBackfill, "").at(1, 1),
+            LintError::new(ErrorCode::UnbatchedBackfill, "").at(3, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_unbatched_backfill_disabled_by_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/insert_select.sql"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_batched_backfill() {
+        let config = Config::parse(r#"enabled = ["UnbatchedBackfill"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/insert_batched.sql"), &config);
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_lock_after_long_write() {
+        let errors = lint_errors(&PathBuf::from("test_data/lock_after_long_write.sql"), &Config::default())
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::LockAfterLongWrite)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![LintError::new(ErrorCode::LockAfterLongWrite, "").at(3, 1)], errors);
+        assert!(errors[0].message().contains("after an UPDATE of every row in characters"));
+    }
+
+    #[test]
+    fn test_lint_lock_before_long_write() {
+        let errors = lint_errors(&PathBuf::from("test_data/multiple_statements.sql"), &Config::default());
+        assert!(errors.iter().all(|e| e.code != ErrorCode::LockAfterLongWrite));
+    }
+
+    #[test]
+    fn test_lint_load_after_lock() {
+        let config = Config::parse(r#"enabled = ["LoadAfterLock"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/load_after_lock.sql"), &config)
+            .into_iter()
+            .filter(|e| e.code == ErrorCode::LoadAfterLock)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![
+            LintError::new(ErrorCode::LoadAfterLock, "").at(3, 1),
+            LintError::new(ErrorCode::LoadAfterLock, "").at(5, 1),
+        ], errors);
+        assert!(errors[0].message().contains("after an ALTER TABLE of characters"));
+        assert!(errors[1].message().contains("after a CREATE INDEX on guilds without CONCURRENTLY"));
+    }
+
+    #[test]
+    fn test_lint_load_after_lock_disabled_by_default() {
+        let errors = lint_errors(&PathBuf::from("test_data/load_after_lock.sql"), &Config::default());
+        assert!(errors.iter().all(|e| e.code != ErrorCode::LoadAfterLock));
+    }
+}
@@ -0,0 +1,55 @@
+//! Output of lint errors as Checkstyle XML, which Jenkins and many other CI plugins can display.
+use std::fmt::Write;
+use crate::junit::escape;
+use crate::{FileResult, Severity};
+
+/// Builds a Checkstyle XML report from the errors found in each file.
+pub(crate) fn report(results: &[FileResult]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n");
+    for FileResult{path: file, errors} in results {
+        writeln!(xml, "  <file name=\"{}\">", escape(file)).unwrap();
+        for error in errors {
+            writeln!(xml, "    <error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\" source=\"{}\"/>",
+                     error.line(), error.column(), severity(error.severity()), escape(error.message()), error.code()).unwrap();
+        }
+        writeln!(xml, "  </file>").unwrap();
+    }
+    xml.push_str("</checkstyle>\n");
+    return xml;
+}
+
+/// Returns the Checkstyle name of a severity.
+fn severity(severity: Severity) -> &'static str {
+    return match severity {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorCode, LintError};
+
+    #[test]
+    fn test_report() {
+        let results = vec![
+            FileResult::new("migrations/a.sql", vec![
+                LintError::new(ErrorCode::NonConcurrentIndex, "Index name_idx on characters is created without CONCURRENTLY").at(2, 1),
+                LintError::new(ErrorCode::UnboundedWrite, "DELETE on characters has no WHERE clause & isn't <batched>").at(5, 3),
+            ]),
+            FileResult::new("migrations/b.sql", vec![]),
+        ];
+        assert_eq!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+  <file name="migrations/a.sql">
+    <error line="2" column="1" severity="warning" message="Index name_idx on characters is created without CONCURRENTLY" source="NonConcurrentIndex"/>
+    <error line="5" column="3" severity="warning" message="DELETE on characters has no WHERE clause &amp; isn&apos;t &lt;batched&gt;" source="UnboundedWrite"/>
+  </file>
+  <file name="migrations/b.sql">
+  </file>
+</checkstyle>
+"#, report(&results));
+    }
+}
@@ -0,0 +1,428 @@
+//! Loading of the linter's configuration file.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use crate::{Dialect, ErrorCode, LintError, PgVersion, Severity, RULES};
+
+/// The config file we look for in the current directory if none is given explicitly.
+pub const DEFAULT_CONFIG_FILE: &str = ".sql-perf-linter.toml";
+
+/// Configuration controlling which rules are applied. It's serialised to build cache keys, so
+/// anything that affects the results needs to be included in that.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Rules that should not be reported.
+    #[serde(default)]
+    disabled: Vec<ErrorCode>,
+    /// Rules that should be reported even though they're off by default.
+    #[serde(default)]
+    enabled: Vec<ErrorCode>,
+    /// The SQL dialect to parse files as.
+    #[serde(default)]
+    dialect: Dialect,
+    /// The oldest version of PostgreSQL that migrations need to run safely against.
+    #[serde(default)]
+    pg_version: Option<PgVersion>,
+    /// Overrides for individual rules. These are ordered so that the config's serialised form is
+    /// stable, since it's used in cache keys.
+    #[serde(default)]
+    rules: BTreeMap<ErrorCode, RuleConfig>,
+    /// Tables that are small enough that locking them briefly doesn't matter.
+    #[serde(default)]
+    small_tables: Vec<String>,
+    /// Tables that are partitioned, so locking them locks every partition too.
+    #[serde(default)]
+    partitioned_tables: Vec<String>,
+    /// Index methods that columns should be indexed with, keyed by glob patterns matching the
+    /// column names.
+    #[serde(default)]
+    index_methods: BTreeMap<String, String>,
+    /// If true, SQL is extracted from execute calls in Ruby and Python migrations and linted.
+    #[serde(default)]
+    embedded_sql: bool,
+    /// If set, only these rules are reported. This can't be set from the config file.
+    #[serde(skip_deserializing)]
+    selected: Option<Vec<ErrorCode>>,
+}
+
+/// Configuration overriding the defaults for a single rule.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    /// The severity to report errors from this rule at.
+    severity: Option<Severity>,
+    /// A message to report instead of the built-in one.
+    message: Option<String>,
+}
+
+impl Config {
+    /// Loads the config from the given file. If none is given, the default file in the
+    /// current directory is used if it exists.
+    pub fn load(file: Option<PathBuf>) -> Result<Config, String> {
+        let path = match file {
+            Some(file) => file,
+            None => {
+                let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if !path.exists() {
+                    return Ok(Config::default());
+                }
+                path
+            },
+        };
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        return Config::parse(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e));
+    }
+
+    /// Writes a commented config file to the given path that leaves everything at its defaults,
+    /// as a starting point to edit. An existing file is only overwritten if force is true.
+    pub fn init(path: &Path, force: bool) -> Result<(), String> {
+        if path.exists() && !force {
+            return Err(format!("Config file {} already exists, pass --force to overwrite it", path.display()));
+        }
+        return fs::write(path, Config::template())
+            .map_err(|e| format!("Failed to write config file {}: {}", path.display(), e));
+    }
+
+    /// Returns the contents of the config file that init writes. Every rule that can be turned on
+    /// or off is listed, with its default severity, under whichever of enabled or disabled would
+    /// change it, commented out.
+    pub fn template() -> String {
+        let lines = |enabled_by_default| RULES.iter()
+            .filter(|rule| !rule.code.always_enabled() && rule.code.enabled_by_default() == enabled_by_default)
+            .map(|rule| format!("    # \"{}\",  # {}: {}\n", rule.name(), format!("{:?}", rule.code.severity()).to_lowercase(), rule.summary))
+            .collect::<String>();
+        return format!(r#"# Configuration for sql-perf-linter. Everything here is the default, so uncomment whatever
+# needs to change. `sql-perf-linter --explain <rule>` describes each rule in detail.
+
+# One of postgres, mysql, sqlite or generic. The performance rules only apply to postgres;
+# other dialects are only checked for syntax.
+dialect = "postgres"
+
+# The oldest version of PostgreSQL that migrations need to run safely against. Some operations are
+# only slow on older versions; if this isn't set, the latest version's behaviour is assumed.
+# pg_version = "11"
+
+# Rules that are on by default. Uncomment any that shouldn't be reported.
+disabled = [
+{}]
+
+# Rules that are off by default. Uncomment any that should be reported.
+enabled = [
+{}]
+
+# Tables small enough that locking them for a full rewrite or scan doesn't matter. Findings that are
+# only a problem because of how long they lock a table are not reported for these.
+small_tables = []
+
+# Partitioned tables (besides ones created with PARTITION BY in the migrations linted), where locking
+# the table locks every partition. The PartitionedTable rule raises findings about locking these.
+partitioned_tables = []
+
+# Set to true to lint the SQL passed to execute in Ruby (Rails) and Python (Alembic) migrations.
+embedded_sql = false
+
+# Index methods that indexes on columns matching each pattern must use, checked by IndexMethod.
+[index_methods]
+# "*_tsv" = "gin"
+
+# Any rule can be reported at a different severity (info, warning or error), or with a different
+# message, in a section of its own.
+# [rules.NonConcurrentIndex]
+# severity = "error"
+# message = "Indexes must be created concurrently."
+"#, lines(true), lines(false));
+    }
+
+    /// Parses the config from a string in TOML format.
+    pub fn parse(contents: &str) -> Result<Config, toml::de::Error> {
+        return toml::from_str(contents);
+    }
+
+    /// Returns the SQL dialect to parse files as.
+    pub fn dialect(&self) -> Dialect {
+        return self.dialect;
+    }
+
+    /// Returns the version of PostgreSQL that rules should consider, the latest if none is set.
+    pub fn pg_version(&self) -> PgVersion {
+        return self.pg_version.unwrap_or(PgVersion::LATEST);
+    }
+
+    /// Overrides the SQL dialect to parse files as.
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    /// Returns true if SQL embedded in Ruby and Python migrations should be extracted and linted.
+    pub fn embedded_sql(&self) -> bool {
+        return self.embedded_sql;
+    }
+
+    /// Sets whether SQL embedded in Ruby and Python migrations is extracted and linted.
+    pub fn set_embedded_sql(&mut self, embedded_sql: bool) {
+        self.embedded_sql = embedded_sql;
+    }
+
+    /// Enables or disables the given rule, overriding anything the config file said about it.
+    pub fn set_enabled(&mut self, code: ErrorCode, enabled: bool) {
+        self.enabled.retain(|c| *c != code);
+        self.disabled.retain(|c| *c != code);
+        if enabled {
+            self.enabled.push(code);
+        } else {
+            self.disabled.push(code);
+        }
+    }
+
+    /// Overrides the severity that errors from the given rule are reported at.
+    pub fn set_severity(&mut self, code: ErrorCode, severity: Severity) {
+        self.rules.entry(code).or_default().severity = Some(severity);
+    }
+
+    /// Restricts the rules that are reported to only the given ones, regardless of whether
+    /// they're enabled by default or by the rest of the config. Errors that mean a file couldn't
+    /// be linted at all are still reported.
+    pub fn select(&mut self, codes: Vec<ErrorCode>) {
+        self.selected = Some(codes);
+    }
+
+    /// Returns true if the given rule is enabled. Errors that mean a file couldn't be linted at
+    /// all are always enabled.
+    pub fn is_enabled(&self, code: ErrorCode) -> bool {
+        if code.always_enabled() {
+            return true;
+        } else if let Some(selected) = &self.selected {
+            return selected.contains(&code);
+        }
+        return if self.enabled.contains(&code) {
+            true
+        } else if self.disabled.contains(&code) {
+            false
+        } else {
+            code.enabled_by_default()
+        };
+    }
+
+    /// Returns true if the given table is listed as a small table.
+    /// Schema-qualified names also match if the list only gives the unqualified name.
+    pub fn is_small_table(&self, table: &str) -> bool {
+        return lists_table(&self.small_tables, table);
+    }
+
+    /// Returns true if the given table is listed as a partitioned table, matching names in the
+    /// same way as is_small_table.
+    pub fn is_partitioned_table(&self, table: &str) -> bool {
+        return lists_table(&self.partitioned_tables, table);
+    }
+
+    /// Returns the index method that the given column is configured to be indexed with, if any.
+    /// Patterns that aren't valid globs have to match the column name exactly.
+    pub fn index_method(&self, column: &str) -> Option<&str> {
+        return self.index_methods.iter()
+            .find(|(pattern, _)| Pattern::new(pattern).map_or(*pattern == column, |p| p.matches(column)))
+            .map(|(_, method)| method.as_str());
+    }
+
+    /// Applies any overrides for the error's rule to it.
+    pub(crate) fn apply(&self, error: LintError) -> LintError {
+        return match self.rules.get(&error.code) {
+            None => error,
+            Some(rule) => LintError{
+                severity: rule.severity.unwrap_or(error.severity),
+                message: rule.message.clone().unwrap_or(error.message),
+                ..error
+            },
+        };
+    }
+}
+
+/// Returns true if the given table is in the given list of them, ignoring case as PostgreSQL
+/// does for unquoted names. Schema-qualified names also match if the list only gives the
+/// unqualified name.
+fn lists_table(tables: &[String], table: &str) -> bool {
+    let table = table.to_lowercase();
+    let unqualified = table.rsplit('.').next().unwrap_or(&table);
+    return tables.iter().map(|t| t.to_lowercase()).any(|t| t == table || t == unqualified);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let config = Config::default();
+        assert!(config.is_enabled(ErrorCode::NonConcurrentIndex));
+    }
+
+    #[test]
+    fn test_parse_disabled() {
+        let config = Config::parse(r#"disabled = ["NonConcurrentIndex", "DefaultValue"]"#).unwrap();
+        assert!(!config.is_enabled(ErrorCode::NonConcurrentIndex));
+        assert!(!config.is_enabled(ErrorCode::DefaultValue));
+        assert!(config.is_enabled(ErrorCode::NotNullColumn));
+        let config = Config::parse(r#"disabled = ["nonconcurrentindex"]"#).unwrap();
+        assert!(!config.is_enabled(ErrorCode::NonConcurrentIndex));
+        let err = Config::parse(r#"disabled = ["NonConcurrentIndexes"]"#).unwrap_err();
+        assert!(err.to_string().contains("Unknown rule NonConcurrentIndexes"));
+    }
+
+    #[test]
+    fn test_parse_enabled() {
+        assert!(!Config::default().is_enabled(ErrorCode::MissingLockTimeout));
+        let config = Config::parse(r#"enabled = ["MissingLockTimeout"]"#).unwrap();
+        assert!(config.is_enabled(ErrorCode::MissingLockTimeout));
+    }
+
+    #[test]
+    fn test_set_enabled() {
+        let mut config = Config::parse(r#"disabled = ["NonConcurrentIndex"]"#).unwrap();
+        config.set_enabled(ErrorCode::NonConcurrentIndex, true);
+        config.set_enabled(ErrorCode::DropColumn, false);
+        assert!(config.is_enabled(ErrorCode::NonConcurrentIndex));
+        assert!(!config.is_enabled(ErrorCode::DropColumn));
+        config.set_enabled(ErrorCode::NonConcurrentIndex, false);
+        assert!(!config.is_enabled(ErrorCode::NonConcurrentIndex));
+    }
+
+    #[test]
+    fn test_set_severity() {
+        let mut config = Config::parse("[rules.DropColumn]\nmessage = \"Don't\"").unwrap();
+        config.set_severity(ErrorCode::DropColumn, Severity::Info);
+        let error = config.apply(LintError::new(ErrorCode::DropColumn, "Column is dropped"));
+        assert_eq!(Severity::Info, error.severity());
+        assert_eq!("Don't", error.message());
+    }
+
+    #[test]
+    fn test_template() {
+        let template = Config::template();
+        assert!(template.contains("    # \"NonConcurrentIndex\",  # warning: "));
+        assert!(template.contains("    # \"MissingLockTimeout\",  # warning: "));
+        assert!(!template.contains("\"FileError\""));
+        assert!(!template.contains("\"SyntaxError\""));
+        let config = Config::parse(&template).unwrap();
+        for code in ErrorCode::ALL {
+            assert_eq!(code.enabled_by_default(), config.is_enabled(*code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_init() {
+        let path = std::env::temp_dir().join(format!("sql-perf-linter-init-test-{}.toml", std::process::id()));
+        Config::init(&path, false).unwrap();
+        assert!(Config::load(Some(path.clone())).is_ok());
+        assert!(Config::init(&path, false).unwrap_err().contains("already exists"));
+        fs::write(&path, "").unwrap();
+        Config::init(&path, true).unwrap();
+        assert_eq!(Config::template(), fs::read_to_string(&path).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_pg_version() {
+        assert_eq!(PgVersion::LATEST, Config::default().pg_version());
+        assert_eq!(PgVersion(10), Config::parse(r#"pg_version = "10""#).unwrap().pg_version());
+        assert_eq!(PgVersion(12), Config::parse("pg_version = 12").unwrap().pg_version());
+        assert_eq!(PgVersion(9), Config::parse(r#"pg_version = "9.6""#).unwrap().pg_version());
+        assert!(Config::parse(r#"pg_version = "latest""#).is_err());
+    }
+
+    #[test]
+    fn test_parse_small_tables() {
+        let config = Config::parse(r#"small_tables = ["feature_flags", "admin.settings"]"#).unwrap();
+        assert!(config.is_small_table("feature_flags"));
+        assert!(config.is_small_table("public.feature_flags"));
+        assert!(config.is_small_table("admin.settings"));
+        assert!(!config.is_small_table("settings"));
+        assert!(!config.is_small_table("characters"));
+        let config = Config::parse(r#"small_tables = ["Feature_Flags"]"#).unwrap();
+        assert!(config.is_small_table("feature_flags"));
+        assert!(config.is_small_table("PUBLIC.FEATURE_FLAGS"));
+    }
+
+    #[test]
+    fn test_parse_partitioned_tables() {
+        let config = Config::parse(r#"partitioned_tables = ["events"]"#).unwrap();
+        assert!(config.is_partitioned_table("public.events"));
+        assert!(!config.is_partitioned_table("characters"));
+        assert!(!config.is_small_table("events"));
+    }
+
+    #[test]
+    fn test_parse_index_methods() {
+        let config = Config::parse(r#"
+            [index_methods]
+            "*_tsv" = "gin"
+            location = "gist"
+        "#).unwrap();
+        assert_eq!(Some("gin"), config.index_method("body_tsv"));
+        assert_eq!(Some("gist"), config.index_method("location"));
+        assert_eq!(None, config.index_method("name"));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut config = Config::parse(r#"disabled = ["NonConcurrentIndex"]"#).unwrap();
+        config.select(vec![ErrorCode::NonConcurrentIndex, ErrorCode::MissingLockTimeout]);
+        assert!(config.is_enabled(ErrorCode::NonConcurrentIndex));
+        assert!(config.is_enabled(ErrorCode::MissingLockTimeout));
+        assert!(!config.is_enabled(ErrorCode::DropColumn));
+        assert!(config.is_enabled(ErrorCode::SyntaxError));
+    }
+
+    #[test]
+    fn test_always_enabled() {
+        let mut config = Config::parse(r#"disabled = ["FileError", "SyntaxError"]"#).unwrap();
+        assert!(config.is_enabled(ErrorCode::FileError));
+        assert!(config.is_enabled(ErrorCode::SyntaxError));
+        config.set_enabled(ErrorCode::SyntaxError, false);
+        assert!(config.is_enabled(ErrorCode::SyntaxError));
+    }
+
+    #[test]
+    fn test_parse_dialect() {
+        assert_eq!(Dialect::Postgres, Config::default().dialect());
+        let config = Config::parse(r#"dialect = "mysql""#).unwrap();
+        assert_eq!(Dialect::MySql, config.dialect());
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let config = Config::parse(r#"
+            [rules.NonConcurrentIndex]
+            severity = "error"
+            message = "See the runbook for how to create indexes"
+
+            [rules.DefaultValue]
+            severity = "info"
+        "#).unwrap();
+        let error = config.apply(LintError::new(ErrorCode::NonConcurrentIndex, "Index is created without CONCURRENTLY"));
+        assert_eq!(Severity::Error, error.severity());
+        assert_eq!("See the runbook for how to create indexes", error.message());
+        let error = config.apply(LintError::new(ErrorCode::DefaultValue, "Column is added with a default"));
+        assert_eq!(Severity::Info, error.severity());
+        assert_eq!("Column is added with a default", error.message());
+        let error = config.apply(LintError::new(ErrorCode::DropColumn, "Column is dropped"));
+        assert_eq!(Severity::Warning, error.severity());
+    }
+
+    #[test]
+    fn test_parse_rules_unknown_rule() {
+        let err = Config::parse("[rules.NonConcurrentIndexes]\nseverity = \"error\"").unwrap_err();
+        assert!(err.to_string().contains("NonConcurrentIndexes"));
+        let err = Config::parse("[rules.NonConcurrentIndex]\nseverty = \"error\"").unwrap_err();
+        assert!(err.to_string().contains("severty"));
+    }
+
+    #[test]
+    fn test_parse_unknown_rule() {
+        let err = Config::parse(r#"disabled = ["NonConcurrentIndexes"]"#).unwrap_err();
+        assert!(err.to_string().contains("NonConcurrentIndexes"));
+    }
+}
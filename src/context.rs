@@ -0,0 +1,188 @@
+//! State that carries over between files, for rules that span several migrations.
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use sqlparser::ast;
+use crate::{Config, ErrorCode, LintError};
+
+/// State accumulated across all the statements linted so far, potentially from many files.
+/// For it to make sense, files have to be linted in the order they'll be applied.
+#[derive(Debug, Default)]
+pub struct LinterContext {
+    /// The name of the file currently being linted.
+    file: String,
+    /// Tables created so far, mapped to the file that created them.
+    tables: HashMap<String, String>,
+    /// Columns that have been made NOT NULL without a default, keyed by table and column name and
+    /// mapped to the file that did it.
+    not_null_columns: HashMap<(String, String), String>,
+    /// Tables created with PARTITION BY so far.
+    partitioned_tables: HashSet<String>,
+    /// The total time spent parsing the files linted so far.
+    parse_time: Duration,
+    /// The total time spent evaluating rules against their statements.
+    rule_time: Duration,
+}
+
+impl LinterContext {
+    pub fn new() -> LinterContext {
+        return LinterContext::default();
+    }
+
+    /// Sets the name of the file whose statements are linted next.
+    pub fn set_file(&mut self, file: &str) {
+        self.file = file.to_string();
+    }
+
+    /// Returns true if the given table was created by a statement linted so far.
+    pub fn has_table(&self, table: &ast::ObjectName) -> bool {
+        return self.tables.contains_key(&key(table));
+    }
+
+    /// Returns true if the given table was created as a partitioned table by a statement linted
+    /// so far. Schema-qualified names also match a table created without the schema.
+    pub fn is_partitioned_table(&self, table: &str) -> bool {
+        let table = table.to_lowercase();
+        let unqualified = table.rsplit('.').next().unwrap_or(&table);
+        return self.partitioned_tables.contains(&table) || self.partitioned_tables.contains(unqualified);
+    }
+
+    /// Returns the total time spent parsing the files linted so far.
+    pub(crate) fn parse_time(&self) -> Duration {
+        return self.parse_time;
+    }
+
+    /// Adds to the total time spent parsing.
+    pub(crate) fn add_parse_time(&mut self, time: Duration) {
+        self.parse_time += time;
+    }
+
+    /// Returns the total time spent evaluating rules against the statements linted so far.
+    pub(crate) fn rule_time(&self) -> Duration {
+        return self.rule_time;
+    }
+
+    /// Adds to the total time spent evaluating rules.
+    pub(crate) fn add_rule_time(&mut self, time: Duration) {
+        self.rule_time += time;
+    }
+
+    /// Records that the given table was created as a partitioned table.
+    pub(crate) fn add_partitioned_table(&mut self, table: &str) {
+        self.partitioned_tables.insert(table.to_lowercase());
+    }
+
+    /// Lints statements that have already been parsed, applying the given configuration and
+    /// carrying over state from anything linted before. They're linted just as they would be from
+    /// source text, but there's none to refer to, so all the errors are positioned at the start.
+    pub fn lint_statements(&mut self, stmts: &[ast::Statement], config: &Config) -> Vec<LintError> {
+        return crate::lint_statements_in(stmts, config, self);
+    }
+
+    /// Records anything about the next statement that later statements need to know. This is done
+    /// once the rules have checked it, so they only see what came before it.
+    pub(crate) fn record(&mut self, stmt: &ast::Statement) {
+        match stmt {
+            ast::Statement::CreateTable{name, ..} => {
+                self.tables.insert(key(name), self.file.clone());
+            },
+            ast::Statement::AlterTable{name, operation: ast::AlterTableOperation::AddColumn(def)} => {
+                let not_null = def.options.iter().any(|opt| opt.option == ast::ColumnOption::NotNull);
+                let default = def.options.iter().any(|opt| match opt.option {
+                    ast::ColumnOption::Default(_) => true,
+                    _ => false,
+                });
+                if not_null && !default {
+                    self.not_null_columns.insert((key(name), def.name.to_string().to_lowercase()), self.file.clone());
+                }
+            },
+            ast::Statement::AlterTable{name, operation: ast::AlterTableOperation::AlterColumn{column_name, op: ast::AlterColumnOperation::SetNotNull}} => {
+                self.not_null_columns.insert((key(name), column_name.to_string().to_lowercase()), self.file.clone());
+            },
+            _ => (),
+        }
+    }
+
+    /// Lints an UPDATE for backfilling columns that a statement before it made NOT NULL.
+    pub(crate) fn lint_backfill(&self, stmt: &ast::Statement) -> Vec<LintError> {
+        return match stmt {
+            ast::Statement::Update{table_name, assignments, ..} => assignments.iter().filter_map(|assignment| {
+                let file = self.not_null_columns.get(&(key(table_name), assignment.id.to_string().to_lowercase()))?;
+                let location = if *file == self.file {
+                    "earlier in this file".to_string()
+                } else {
+                    format!("in {}", file)
+                };
+                Some(LintError::new(ErrorCode::NotNullBeforeBackfill, format!(
+                    "Column {} on table {} is backfilled after it was made NOT NULL ({}). On a table with existing rows, the constraint should only be added once every row has a value.",
+                    assignment.id, table_name, location).as_str()))
+            }).collect(),
+            _ => Vec::new(),
+        };
+    }
+}
+
+/// Returns the key we track a table under.
+fn key(table: &ast::ObjectName) -> String {
+    return table.to_string().to_lowercase();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Severity;
+    use sqlparser::dialect::PostgreSqlDialect;
+    use sqlparser::parser::Parser;
+
+    fn parse(sql: &str) -> Vec<ast::Statement> {
+        return Parser::parse_sql(&PostgreSqlDialect{}, sql.to_string()).unwrap();
+    }
+
+    fn table_name(table: &str) -> ast::ObjectName {
+        return match parse(&format!("DROP TABLE {}", table)).remove(0) {
+            ast::Statement::Drop{mut names, ..} => names.remove(0),
+            stmt => panic!("Unexpected statement {}", stmt),
+        };
+    }
+
+    #[test]
+    fn test_not_null_before_backfill_across_files() {
+        let mut context = LinterContext::new();
+        context.set_file("0005_add_column.sql");
+        let errors = context.lint_statements(&parse("ALTER TABLE characters ALTER COLUMN name SET NOT NULL;"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::SetNotNull, "")], errors);
+        context.set_file("0006_backfill.sql");
+        let errors = context.lint_statements(&parse("UPDATE characters SET name = 'unknown' WHERE name IS NULL;"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::NotNullBeforeBackfill, "")], errors);
+        assert!(errors[0].message().contains("in 0005_add_column.sql"));
+    }
+
+    #[test]
+    fn test_backfill_before_not_null() {
+        let mut context = LinterContext::new();
+        context.set_file("0005_backfill.sql");
+        let errors = context.lint_statements(&parse("UPDATE characters SET name = 'unknown' WHERE name IS NULL;"), &Config::default());
+        assert_eq!(0, errors.len());
+    }
+
+    #[test]
+    fn test_lint_statements_as_from_source() {
+        let stmts = parse("CREATE INDEX CONCURRENTLY a_idx ON characters (name);\nCREATE INDEX CONCURRENTLY b_idx ON characters (name);\nUPDATE characters SET level = 1;");
+        let mut config = Config::default();
+        config.set_severity(ErrorCode::UnboundedWrite, Severity::Error);
+        let errors = LinterContext::new().lint_statements(&stmts, &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DuplicateIndex, ""),
+            LintError::new(ErrorCode::UnboundedWrite, ""),
+        ], errors);
+        assert_eq!(Severity::Error, errors[1].severity());
+    }
+
+    #[test]
+    fn test_has_table() {
+        let mut context = LinterContext::new();
+        context.set_file("0001_create.sql");
+        context.lint_statements(&parse("CREATE TABLE Guilds (id BIGINT);"), &Config::default());
+        assert!(context.has_table(&table_name("guilds")));
+        assert!(!context.has_table(&table_name("characters")));
+    }
+}
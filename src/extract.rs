@@ -0,0 +1,208 @@
+//! Extraction of SQL from migrations written in other languages, which pass it as string literals
+//! to an execute function, as in `execute <<~SQL` in Rails or `op.execute("""...""")` in Alembic.
+//! This is deliberately simple-minded: anything that isn't a literal (e.g. a variable or an
+//! interpolation) isn't understood, and is linted as whatever text it happens to be.
+use std::path::Path;
+
+/// The extensions of files that SQL is extracted from, rather than linted as it is.
+pub const EMBEDDED_EXTENSIONS: &[&str] = &["rb", "py"];
+
+/// The name of the function that SQL is passed to in the languages we understand.
+const EXECUTE: &str = "execute";
+
+/// Returns the pieces of SQL in the given file, along with the number of lines before the one
+/// each of them starts on. Files that aren't in one of the languages we extract from are a single
+/// piece starting on the first line.
+pub fn extract_sql(path: &Path, contents: &str) -> Vec<(usize, String)> {
+    return pieces(path, contents).into_iter().map(|piece| (piece.line, piece.sql)).collect();
+}
+
+/// A piece of SQL found in a file.
+#[derive(Debug, PartialEq)]
+struct Piece {
+    /// The number of lines before the one it starts on.
+    line: usize,
+    /// The column (1-based, in characters) it starts at on that line.
+    column: usize,
+    sql: String,
+}
+
+/// As extract_sql, but with the column each piece starts at too.
+fn pieces(path: &Path, contents: &str) -> Vec<Piece> {
+    return if is_embedded(path) {
+        execute_calls(contents)
+    } else {
+        vec![Piece{line: 0, column: 1, sql: contents.to_string()}]
+    };
+}
+
+/// Returns true if the given file is in one of the languages we extract SQL from.
+pub(crate) fn is_embedded(path: &Path) -> bool {
+    return path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| EMBEDDED_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+}
+
+/// Returns the SQL in the given file as a single script, with each piece of it at the same line
+/// and column as it was found at, so errors in it are reported at the right place in the original
+/// file. Each piece ends with a semicolon, so pieces that leave it off don't run into one another.
+pub(crate) fn embedded_sql(path: &Path, contents: &str) -> String {
+    let mut sql = String::new();
+    let mut line = 0;
+    let mut column = 1;
+    for piece in pieces(path, contents) {
+        let text = piece.sql.trim_end();
+        if text.trim_start().is_empty() {
+            continue;
+        }
+        if line < piece.line {
+            sql.push_str(&"\n".repeat(piece.line - line));
+            line = piece.line;
+            column = 1;
+        }
+        // Pieces on the same line always start after the end of the one before (and its
+        // semicolon, which takes the place of its closing quote), but be safe if they don't.
+        if column < piece.column {
+            sql.push_str(&" ".repeat(piece.column - column));
+        } else if !sql.is_empty() && !sql.ends_with('\n') {
+            sql.push(' ');
+        }
+        sql.push_str(text);
+        line += text.matches('\n').count();
+        if !text.ends_with(';') {
+            sql.push(';');
+        }
+        column = sql.rsplit('\n').next().unwrap().chars().count() + 1;
+    }
+    if !sql.is_empty() && !sql.ends_with('\n') {
+        sql.push('\n');
+    }
+    return sql;
+}
+
+/// Finds the string literals passed to execute calls, in Ruby or Python syntax.
+fn execute_calls(contents: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    while let Some(i) = contents[start..].find(EXECUTE) {
+        let i = start + i;
+        start = i + EXECUTE.len();
+        let preceded = contents[..i].chars().next_back().map_or(false, |c| c.is_alphanumeric() || c == '_');
+        if preceded {
+            continue;
+        }
+        if let Some((offset, end, piece)) = literal(contents, start) {
+            let line_start = contents[..offset].rfind('\n').map_or(0, |n| n + 1);
+            pieces.push(Piece{
+                line: contents[..offset].matches('\n').count(),
+                column: contents[line_start..offset].chars().count() + 1,
+                sql: piece,
+            });
+            start = end;
+        }
+    }
+    return pieces;
+}
+
+/// Parses the string literal that's the first argument to a call, whose name ends at the given
+/// index. Returns the index its contents start at, the index just after it, and its contents.
+fn literal(contents: &str, name_end: usize) -> Option<(usize, usize, String)> {
+    let rest = &contents[name_end..];
+    let args = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+    // Ruby allows calls without parentheses, but then there has to be a space before the argument.
+    if args.len() == rest.len() {
+        return None;
+    }
+    let start = name_end + rest.len() - args.len();
+    if let Some(heredoc) = args.strip_prefix("<<") {
+        return heredoc_literal(contents, start + 2, heredoc);
+    }
+    for quote in &["\"\"\"", "'''"] {
+        if let Some(body) = args.strip_prefix(quote) {
+            let len = body.find(quote)?;
+            let offset = start + quote.len();
+            return Some((offset, offset + len + quote.len(), body[..len].to_string()));
+        }
+    }
+    let quote = args.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let mut piece = String::new();
+    let mut chars = args.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            return Some((start + 1, start + i + 1, piece));
+        } else if c == '\\' {
+            let (_, escaped) = chars.next()?;
+            if escaped != quote && escaped != '\\' {
+                piece.push(c);
+            }
+            piece.push(escaped);
+        } else {
+            piece.push(c);
+        }
+    }
+    return None;
+}
+
+/// Parses a Ruby heredoc, e.g. <<~SQL, whose identifier starts at the given index.
+/// Its contents start on the line after it, and run until a line consisting only of the identifier.
+fn heredoc_literal(contents: &str, start: usize, heredoc: &str) -> Option<(usize, usize, String)> {
+    let ident = heredoc.trim_start_matches(|c| c == '~' || c == '-' || c == '\'' || c == '"');
+    let len = ident.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(ident.len());
+    if len == 0 {
+        return None;
+    }
+    let ident = &ident[..len];
+    let offset = start + contents[start..].find('\n')? + 1;
+    let mut end = offset;
+    for line in contents[offset..].split_inclusive('\n') {
+        if line.trim() == ident {
+            return Some((offset, end + line.len(), contents[offset..end].to_string()));
+        }
+        end += line.len();
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_sql_plain() {
+        assert_eq!(vec![(0, "SELECT 1;\n".to_string())], extract_sql(Path::new("0001.sql"), "SELECT 1;\n"));
+    }
+
+    #[test]
+    fn test_extract_sql_python() {
+        let contents = "def upgrade():\n    op.execute(\"\"\"\n        CREATE INDEX name_idx ON characters (name)\n    \"\"\")\n    op.execute('DROP TABLE \\'guilds\\'')\n    op.execute_if_exists('SELECT 1')\n";
+        assert_eq!(vec![
+            (1, "\n        CREATE INDEX name_idx ON characters (name)\n    ".to_string()),
+            (4, "DROP TABLE 'guilds'".to_string()),
+        ], extract_sql(Path::new("versions/0001_add_index.py"), contents));
+    }
+
+    #[test]
+    fn test_extract_sql_ruby() {
+        let contents = "class AddIndex < ActiveRecord::Migration[6.0]\n  def up\n    execute <<~SQL\n      CREATE INDEX name_idx ON characters (name);\n    SQL\n    execute \"DROP TABLE guilds\"\n  end\nend\n";
+        assert_eq!(vec![
+            (3, "      CREATE INDEX name_idx ON characters (name);\n".to_string()),
+            (5, "DROP TABLE guilds".to_string()),
+        ], extract_sql(Path::new("db/migrate/0001_add_index.rb"), contents));
+    }
+
+    #[test]
+    fn test_embedded_sql() {
+        let contents = "    execute <<~SQL\n      CREATE INDEX name_idx ON characters (name);\n    SQL\n    execute \"DROP TABLE guilds\"; execute 'DROP TABLE characters'\n";
+        assert_eq!("\n      CREATE INDEX name_idx ON characters (name);\n\n             DROP TABLE guilds;           DROP TABLE characters;\n",
+                   embedded_sql(Path::new("0001.rb"), contents));
+    }
+
+    #[test]
+    fn test_embedded_sql_columns() {
+        // Every piece of SQL should be at the same column as in the original line.
+        let line = "    execute \"DROP TABLE guilds\"; execute 'DROP TABLE characters'";
+        let sql = embedded_sql(Path::new("0001.rb"), line);
+        assert_eq!(line.find("DROP TABLE guilds"), sql.find("DROP TABLE guilds"));
+        assert_eq!(line.find("DROP TABLE characters"), sql.find("DROP TABLE characters"));
+    }
+}
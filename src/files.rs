@@ -0,0 +1,154 @@
+//! Expansion of the paths given on the command line into the set of files to lint.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use walkdir::WalkDir;
+
+/// The file in a directory being walked that lists paths to skip, in gitignore syntax.
+pub const IGNORE_FILE: &str = ".sqllintignore";
+
+/// Expands the given paths into the files to lint.
+/// Directories are walked recursively to find files matching the given pattern, or with a .sql
+/// extension (in any case) if there isn't one. Paths in them are skipped if they match the
+/// directory's ignore file or any of the excludes, which use the same gitignore syntax.
+/// Anything else is returned as-is, so paths that don't exist are reported as errors when we try
+/// to lint them, and files given explicitly are linted even if they'd otherwise be ignored.
+pub fn expand_paths(paths: Vec<PathBuf>, pattern: Option<&Pattern>, excludes: &[String]) -> Vec<PathBuf> {
+    return paths.into_iter().flat_map(|path| {
+        if path.is_dir() {
+            walk(&path, pattern, excludes)
+        } else {
+            vec![path]
+        }
+    }).collect();
+}
+
+fn walk(dir: &Path, pattern: Option<&Pattern>, excludes: &[String]) -> Vec<PathBuf> {
+    let ignore = ignore_matcher(dir, excludes);
+    // Following links makes walkdir check for symlink loops for us.
+    return WalkDir::new(dir)
+        .follow_links(true)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !ignore.matched(entry.path(), entry.file_type().is_dir()).is_ignore())
+        .filter_map(|entry| match entry {
+            Err(e) => {
+                warn!("Skipping {}", e);
+                None
+            },
+            Ok(entry) => Some(entry),
+        })
+        .filter(|entry| entry.file_type().is_file() && matches(entry.path(), pattern))
+        .map(|entry| entry.into_path())
+        .collect();
+}
+
+/// Builds a matcher for the paths to skip under the given directory.
+/// Problems with the ignore file or excludes are logged, and the offending lines skipped.
+fn ignore_matcher(dir: &Path, excludes: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    let ignore_file = dir.join(IGNORE_FILE);
+    if ignore_file.is_file() {
+        if let Some(e) = builder.add(&ignore_file) {
+            warn!("Error reading {}: {}", ignore_file.display(), e);
+        }
+    }
+    for exclude in excludes {
+        if let Err(e) = builder.add_line(None, exclude) {
+            warn!("Invalid exclude {}: {}", exclude, e);
+        }
+    }
+    return builder.build().unwrap_or_else(|e| {
+        warn!("Failed to build ignore rules for {}: {}", dir.display(), e);
+        Gitignore::empty()
+    });
+}
+
+/// Returns the files that have changed between the given git ref and HEAD, limited to those under
+/// the given paths if there are any, and matching the pattern (or with a .sql extension) as
+/// for directories. Files that were deleted are left out, since there's nothing to lint.
+/// Paths are relative to the current directory, which has to be inside a git repository.
+pub fn changed_files(base: &str, paths: &[PathBuf], pattern: Option<&Pattern>) -> Result<Vec<PathBuf>, String> {
+    let output = Command::new("git")
+        .args(&["diff", "--name-only", "--relative", "--diff-filter=d"])
+        .arg(format!("{}...HEAD", base))
+        .arg("--")
+        .args(paths)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to find files changed since {}: {}", base, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    return Ok(diff_files(&String::from_utf8_lossy(&output.stdout), pattern));
+}
+
+/// Returns the files to lint from the output of git diff --name-only.
+fn diff_files(output: &str, pattern: Option<&Pattern>) -> Vec<PathBuf> {
+    return output.lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| matches(path, pattern))
+        .collect();
+}
+
+fn matches(path: &Path, pattern: Option<&Pattern>) -> bool {
+    return match pattern {
+        Some(pattern) => pattern.matches_path(path),
+        None => path.extension().map_or(false, |ext| ext.to_string_lossy().eq_ignore_ascii_case("sql")),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_files() {
+        let output = "migrations/0001_create.sql\nREADME.md\nmigrations/0002_INDEX.SQL\n\n";
+        assert_eq!(vec![PathBuf::from("migrations/0001_create.sql"), PathBuf::from("migrations/0002_INDEX.SQL")],
+                   diff_files(output, None));
+        let pattern = Pattern::new("*.md").unwrap();
+        assert_eq!(vec![PathBuf::from("README.md")], diff_files(output, Some(&pattern)));
+    }
+
+    #[test]
+    fn test_expand_directory() {
+        let files = expand_paths(vec![PathBuf::from("test_data/recursive")], None, &[]);
+        assert_eq!(vec![
+            PathBuf::from("test_data/recursive/a.sql"),
+            PathBuf::from("test_data/recursive/nested/b.SQL"),
+        ], files);
+    }
+
+    #[test]
+    fn test_expand_pattern() {
+        let pattern = Pattern::new("*.txt").unwrap();
+        let files = expand_paths(vec![PathBuf::from("test_data/recursive")], Some(&pattern), &[]);
+        assert_eq!(vec![PathBuf::from("test_data/recursive/nested/c.txt")], files);
+    }
+
+    #[test]
+    fn test_expand_files_unchanged() {
+        let paths = vec![PathBuf::from("test_data/create_table.sql"), PathBuf::from("-"), PathBuf::from("does_not_exist.sql")];
+        assert_eq!(paths.clone(), expand_paths(paths, None, &[]));
+    }
+
+    #[test]
+    fn test_expand_ignored() {
+        let files = expand_paths(vec![PathBuf::from("test_data/ignored")], None, &["vendor/".to_string()]);
+        assert_eq!(vec![PathBuf::from("test_data/ignored/a.sql")], files);
+        // Without the exclude only the ignore file applies.
+        let files = expand_paths(vec![PathBuf::from("test_data/ignored")], None, &[]);
+        assert_eq!(vec![
+            PathBuf::from("test_data/ignored/a.sql"),
+            PathBuf::from("test_data/ignored/vendor/c.sql"),
+        ], files);
+    }
+
+    #[test]
+    fn test_expand_ignored_explicit_file() {
+        let paths = vec![PathBuf::from("test_data/ignored/generated/b.sql")];
+        assert_eq!(paths.clone(), expand_paths(paths, None, &["*.sql".to_string()]));
+    }
+}
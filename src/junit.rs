@@ -0,0 +1,68 @@
+//! Output of lint errors as JUnit XML, which many CI systems know how to display.
+//! Each file is a testsuite and each error in it a failing testcase; files without any errors get
+//! a single passing testcase so they still show up in the report.
+use std::fmt::Write;
+use crate::FileResult;
+
+/// Builds a JUnit XML report from the errors found in each file.
+pub(crate) fn report(results: &[FileResult]) -> String {
+    let tests = results.iter().map(|result| std::cmp::max(result.errors.len(), 1)).sum::<usize>();
+    let failures = results.iter().map(|result| result.errors.len()).sum::<usize>();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(xml, "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\">", env!("CARGO_PKG_NAME"), tests, failures).unwrap();
+    for FileResult{path: file, errors} in results {
+        let file = escape(file);
+        writeln!(xml, "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">", file, std::cmp::max(errors.len(), 1), errors.len()).unwrap();
+        if errors.is_empty() {
+            writeln!(xml, "    <testcase name=\"{}\" classname=\"{}\"/>", file, file).unwrap();
+        }
+        for error in errors {
+            let code = error.code().to_string();
+            let message = escape(error.message());
+            writeln!(xml, "    <testcase name=\"{}:{}:{}\" classname=\"{}\">", code, error.line(), error.column(), file).unwrap();
+            writeln!(xml, "      <failure type=\"{}\" message=\"{}\">{}:{}:{}: {}</failure>",
+                     code, message, file, error.line(), error.column(), message).unwrap();
+            writeln!(xml, "    </testcase>").unwrap();
+        }
+        writeln!(xml, "  </testsuite>").unwrap();
+    }
+    xml.push_str("</testsuites>\n");
+    return xml;
+}
+
+/// Escapes text for use in XML content or attribute values.
+pub(crate) fn escape(s: &str) -> String {
+    return s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorCode, LintError};
+
+    #[test]
+    fn test_report() {
+        let results = vec![
+            FileResult::new("a.sql", vec![
+                LintError::new(ErrorCode::UnboundedWrite, "DELETE on characters has no WHERE clause & isn't <batched>").at(3, 1),
+            ]),
+            FileResult::new("b.sql", vec![]),
+        ];
+        assert_eq!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="sql-perf-linter" tests="2" failures="1">
+  <testsuite name="a.sql" tests="1" failures="1">
+    <testcase name="UnboundedWrite:3:1" classname="a.sql">
+      <failure type="UnboundedWrite" message="DELETE on characters has no WHERE clause &amp; isn&apos;t &lt;batched&gt;">a.sql:3:1: DELETE on characters has no WHERE clause &amp; isn&apos;t &lt;batched&gt;</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="b.sql" tests="1" failures="0">
+    <testcase name="b.sql" classname="b.sql"/>
+  </testsuite>
+</testsuites>
+"#, report(&results));
+    }
+}
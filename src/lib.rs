@@ -1,108 +1,1821 @@
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+use owo_colors::OwoColorize;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use sqlparser::ast;
 use sqlparser::dialect;
 use sqlparser::parser::Parser;
 #[macro_use]
 extern crate log;
 
-/// Lint the given set of files for errors and print them to stdout.
-/// Returns true if successful, false if errors occurred.
-pub fn lint(files: Vec<PathBuf>) -> bool {
-    return files.iter().fold(true, |success, file| success && lint_one(file));
+mod baseline;
+mod cache;
+mod check;
+mod checkstyle;
+mod config;
+mod context;
+mod extract;
+mod files;
+mod junit;
+mod rules;
+mod sarif;
+mod timing;
+mod watch;
+use cache::Cache;
+use check::index::IndexDefinition;
+use check::tables::lint_alter_table;
+use check::transaction::LockTimeout;
+use check::unparsed::is_create_partitioned_table;
+use timing::FileTiming;
+pub use baseline::Baseline;
+pub use check::{default_checks, CheckContext, Linter, Rule};
+pub use config::{Config, DEFAULT_CONFIG_FILE};
+pub use context::LinterContext;
+pub use extract::{extract_sql, EMBEDDED_EXTENSIONS};
+pub use files::{changed_files, expand_paths};
+pub use rules::{find_rule, print_rules, rule, RuleInfo, RULES};
+pub use watch::watch;
+
+/// The version of the linter. This is included in machine-readable output so results can be
+/// correlated with the set of rules that produced them.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The exit code when linting fails because of findings below error severity.
+pub const EXIT_WARNINGS: i32 = 1;
+/// The exit code when linting fails because of findings of error severity.
+pub const EXIT_ERRORS: i32 = 2;
+/// The exit code when a file couldn't be read or parsed, or the linter couldn't run at all.
+pub const EXIT_INTERNAL_ERROR: i32 = 3;
+
+/// The errors found in a single file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileResult {
+    /// The name the file is reported under.
+    pub path: String,
+    /// The errors found in it, ordered by position.
+    pub errors: Vec<LintError>,
+}
+
+impl FileResult {
+    pub fn new(path: &str, errors: Vec<LintError>) -> FileResult {
+        return FileResult{path: path.to_string(), errors: errors};
+    }
+
+    /// Returns true if this file would count as success on its own according to the options.
+    pub fn passed(&self, options: &Options) -> bool {
+        return passed(std::slice::from_ref(self), options);
+    }
+}
+
+/// The results of linting a set of files, in the same order as the files were given.
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    pub files: Vec<FileResult>,
+}
+
+impl LintReport {
+    /// Returns the total number of errors found across all the files.
+    pub fn error_count(&self) -> usize {
+        return self.files.iter().map(|file| file.errors.len()).sum();
+    }
+
+    /// Writes the report in the format the options ask for, to their output file or to stdout.
+    /// With write_baseline or count set, writes the baseline or the count instead.
+    pub fn write(&self, options: &Options) -> Result<(), String> {
+        if let Some(path) = &options.write_baseline {
+            let baseline = Baseline::new(&self.files);
+            baseline.save(path)?;
+            eprintln!("Wrote {} to baseline {}", plural(baseline.len(), "finding"), path.display());
+            return Ok(());
+        }
+        if options.count {
+            println!("{}", self.error_count());
+            return Ok(());
+        }
+        if options.quiet {
+            return Ok(());
+        }
+        match &options.output {
+            Some(path) => fs::File::create(path)
+                .and_then(|file| write_report(&mut io::BufWriter::new(file), &self.files, options))
+                .map_err(|e| format!("Failed to write output to {}: {}", path.display(), e))?,
+            None => write_report(&mut io::stdout().lock(), &self.files, options)
+                .map_err(|e| format!("Failed to write output: {}", e))?,
+        }
+        if options.summary {
+            if let Some(summary) = summary(&self.files) {
+                eprintln!("{}", summary);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Returns true if this report counts as success according to the options. Writing a
+    /// baseline or only counting the errors always succeeds.
+    pub fn passed(&self, options: &Options) -> bool {
+        return options.write_baseline.is_some() || options.count || passed(&self.files, options);
+    }
+
+    /// Returns the exit code for this report: EXIT_INTERNAL_ERROR if any file couldn't be read or
+    /// parsed, whatever their severity, otherwise 0 if it passed, or EXIT_ERRORS or EXIT_WARNINGS
+    /// depending on whether any of the findings are of error severity. Writing a baseline or only
+    /// counting the errors always exits with 0.
+    pub fn exit_code(&self, options: &Options) -> i32 {
+        if options.write_baseline.is_some() || options.count {
+            return 0;
+        }
+        let any = |f: fn(&LintError) -> bool| self.files.iter().flat_map(|file| &file.errors).any(f);
+        return if any(|e| e.code.always_enabled()) {
+            EXIT_INTERNAL_ERROR
+        } else if self.passed(options) {
+            0
+        } else if any(|e| e.severity == Severity::Error) {
+            EXIT_ERRORS
+        } else {
+            EXIT_WARNINGS
+        };
+    }
+
+    /// Returns a line explaining why this report failed, e.g.
+    ///     FAILED: 3 errors, 5 warnings (threshold=warning)
+    pub fn exit_reason(&self, options: &Options) -> String {
+        return exit_reason(&self.files, options);
+    }
+
+    /// Returns a line for each file giving its path and whether it passed, separated by a tab, e.g.
+    ///     migrations/0001_create.sql	PASS
+    pub fn file_statuses(&self, options: &Options) -> String {
+        return self.files.iter()
+            .map(|file| format!("{}\t{}\n", file.path, if file.passed(options) { "PASS" } else { "FAIL" }))
+            .collect();
+    }
+}
+
+/// Lints the given set of files for errors, with the default options.
+/// A file named "-" is read from stdin. Rules disabled by the config are not reported.
+pub fn lint_all(files: &[PathBuf], config: &Config) -> LintReport {
+    return lint_files(files, config, &Options::default());
+}
+
+/// Lints the given set of files for errors, applying the options that affect which are found
+/// (such as the baseline and dedupe); the ones about output are ignored.
+/// Files are linted in parallel, but the report is always in the same order as the input.
+/// In ordered mode they're linted one at a time instead, sharing a context between them.
+pub fn lint_files(files: &[PathBuf], config: &Config, options: &Options) -> LintReport {
+    return lint_files_with(files, config, options, &|_| ());
+}
+
+/// As lint_files, but each file's results are also passed to the given function as soon as
+/// they're found, rather than only being available once all the files are done. When linting in
+/// parallel that's in whatever order they finish in, but the report is still in the input order.
+pub fn lint_files_with(files: &[PathBuf], config: &Config, options: &Options, on_file: &(dyn Fn(&FileResult) + Sync)) -> LintReport {
+    let start = Instant::now();
+    let linter = Linter::new();
+    let (results, timings): (Vec<_>, Vec<_>) = if options.ordered {
+        let mut context = LinterContext::new();
+        files.iter().map(|file| {
+            let file_start = Instant::now();
+            let (parse_time, rule_time) = (context.parse_time(), context.rule_time());
+            let name = display_name(file, options.stdin_filename.as_deref());
+            debug!("Linting {}...", name);
+            context.set_file(&name);
+            let results = if options.stdin_file_markers && is_stdin(file) {
+                lint_marked_stdin(&linter, name.clone(), config, &mut context)
+            } else {
+                let errors = lint_errors_in(&linter, file, config, &mut context);
+                vec![FileResult{path: name.clone(), errors: errors}]
+            };
+            let timing = FileTiming{path: name, parse: context.parse_time() - parse_time, rules: context.rule_time() - rule_time, total: file_start.elapsed()};
+            (results.into_iter().map(|result| finish_file(result, options)).inspect(|result| on_file(result)).collect::<Vec<_>>(), timing)
+        }).unzip()
+    } else {
+        let cache = options.cache_dir.clone().map(Cache::new);
+        let lint = |file: &PathBuf| lint_independently(&linter, file, config, options, cache.as_ref(), on_file);
+        // Either way the results come out in the same order as the files.
+        if options.parallel {
+            files.par_iter().map(lint).unzip()
+        } else {
+            files.iter().map(lint).unzip()
+        }
+    };
+    if options.timing {
+        eprint!("{}", timing::report(&timings, start.elapsed()));
+    }
+    return LintReport{files: results.into_iter().flatten().collect()};
+}
+
+/// Lints the given files, writing the findings in each one as NDJSON to the output file or stdout
+/// as soon as that file has been linted, followed by the summary if the options ask for it.
+/// When linting in parallel, files are written in the order they finish in rather than the order
+/// given (although the findings in each one stay in order), so a consumer can start on them early.
+pub fn lint_files_streaming(files: &[PathBuf], config: &Config, options: &Options) -> Result<LintReport, String> {
+    let target = options.output.as_ref().map_or(String::new(), |path| format!(" to {}", path.display()));
+    let out: Box<dyn Write + Send> = match &options.output {
+        Some(path) => Box::new(fs::File::create(path).map_err(|e| format!("Failed to write output{}: {}", target, e))?),
+        None => Box::new(io::stdout()),
+    };
+    // Once writing fails we stop trying, and report the error at the end.
+    let out = Mutex::new((out, Ok(())));
+    let report = lint_files_with(files, config, options, &|result| {
+        let mut out = out.lock().unwrap();
+        let (out, status) = &mut *out;
+        if status.is_ok() {
+            *status = write_ndjson(out, std::slice::from_ref(result)).and_then(|()| out.flush());
+        }
+    });
+    let (_, status) = out.into_inner().unwrap();
+    status.map_err(|e| format!("Failed to write output{}: {}", target, e))?;
+    if options.summary {
+        if let Some(summary) = summary(&report.files) {
+            eprintln!("{}", summary);
+        }
+    }
+    return Ok(report);
+}
+
+/// Lints a single file on its own, without any state carried over from other files.
+/// Returns the results along with how long it took.
+fn lint_independently(linter: &Linter, file: &PathBuf, config: &Config, options: &Options, cache: Option<&Cache>,
+                      on_file: &(dyn Fn(&FileResult) + Sync)) -> (Vec<FileResult>, FileTiming) {
+    let start = Instant::now();
+    let name = display_name(file, options.stdin_filename.as_deref());
+    debug!("Linting {}...", name);
+    let mut context = LinterContext::new();
+    let results = if options.stdin_file_markers && is_stdin(file) {
+        lint_marked_stdin(linter, name.clone(), config, &mut context)
+    } else {
+        vec![FileResult{path: name.clone(), errors: match cache {
+            Some(cache) => lint_errors_cached(linter, file, config, cache, &mut context),
+            None => lint_errors_in(linter, file, config, &mut context),
+        }}]
+    };
+    let timing = FileTiming{path: name, parse: context.parse_time(), rules: context.rule_time(), total: start.elapsed()};
+    return (results.into_iter().map(|result| finish_file(result, options)).inspect(|result| on_file(result)).collect(), timing);
+}
+
+/// Lint the given set of files for errors and print them to stdout as the options describe.
+/// Returns true if successful, false if any errors at or above the fail_on severity occurred.
+#[deprecated(note = "use lint_files, then write the LintReport it returns")]
+pub fn lint(files: Vec<PathBuf>, config: &Config, options: &Options) -> bool {
+    let report = lint_files(&files, config, options);
+    if let Err(e) = report.write(options) {
+        eprintln!("{}", e);
+        return false;
+    }
+    let passed = report.passed(options);
+    if !passed && options.print_exit_reason {
+        eprintln!("{}", report.exit_reason(options));
+    }
+    if options.per_file_exit {
+        eprint!("{}", report.file_statuses(options));
+    }
+    return passed;
+}
+
+/// Returns a line explaining why the given results failed.
+fn exit_reason(results: &[FileResult], options: &Options) -> String {
+    let count = |severity| results.iter().flat_map(|result| &result.errors).filter(|e| e.severity == severity).count();
+    let mut counts = vec![plural(count(Severity::Error), "error"), plural(count(Severity::Warning), "warning")];
+    if options.fail_on == Severity::Info {
+        counts.push(format!("{} info", count(Severity::Info)));
+    }
+    let mut threshold = format!("threshold={}", format!("{:?}", options.fail_on).to_lowercase());
+    if let Some(max_warnings) = options.max_warnings {
+        threshold.push_str(&format!(", max-warnings={}", max_warnings));
+    }
+    return format!("FAILED: {} ({})", counts.join(", "), threshold);
+}
+
+/// Applies the options that affect which of a file's errors are reported.
+fn finish_file(result: FileResult, options: &Options) -> FileResult {
+    let FileResult{path, errors} = result;
+    let errors = match &options.baseline {
+        Some(baseline) => baseline.filter(&path, errors),
+        None => errors,
+    };
+    let mut errors = if options.dedupe { dedupe(errors) } else { errors };
+    errors.sort_by_key(|e| (e.line, e.column));
+    return FileResult{path: path, errors: errors};
+}
+
+/// Collapses errors with the same code on the same line into the first of them, noting how many
+/// there were in its message.
+fn dedupe(errors: Vec<LintError>) -> Vec<LintError> {
+    let mut deduped: Vec<(LintError, usize)> = Vec::new();
+    for error in errors {
+        match deduped.iter_mut().find(|(e, _)| e.code == error.code && e.line == error.line) {
+            Some((_, count)) => *count += 1,
+            None => deduped.push((error, 1)),
+        }
+    }
+    return deduped.into_iter().map(|(e, count)| {
+        if count == 1 {
+            e
+        } else {
+            LintError{message: format!("{} ({} occurrences)", e.message, count), ..e}
+        }
+    }).collect();
+}
+
+/// Returns true if the given results count as success according to the options.
+fn passed(results: &[FileResult], options: &Options) -> bool {
+    return match options.max_warnings {
+        None => results.iter().all(|result| succeeded(&result.errors, options.fail_on)),
+        Some(max_warnings) => {
+            let warnings = results.iter().flat_map(|result| &result.errors).filter(|e| e.severity >= Severity::Warning).count();
+            let fail_on = std::cmp::max(options.fail_on, Severity::Error);
+            warnings <= max_warnings && results.iter().all(|result| succeeded(&result.errors, fail_on))
+        },
+    };
+}
+
+/// Options controlling how errors are reported.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The format to print errors in.
+    pub format: Format,
+    /// The minimum severity of error that counts as a failure.
+    pub fail_on: Severity,
+    /// If true, nothing is printed; only the return value indicates success.
+    pub quiet: bool,
+    /// If true, a summary of the errors found is printed to stderr at the end.
+    pub summary: bool,
+    /// If set, up to this many errors of warning severity or above are tolerated before failing.
+    /// fail_on then only applies to errors of error severity.
+    pub max_warnings: Option<usize>,
+    /// If true, text output is coloured.
+    pub color: bool,
+    /// If true, errors with the same code on the same line are collapsed into one.
+    pub dedupe: bool,
+    /// The name to report errors in SQL read from stdin under, instead of "<stdin>".
+    pub stdin_filename: Option<String>,
+    /// If set, results are cached in this directory so unchanged files aren't linted again.
+    pub cache_dir: Option<PathBuf>,
+    /// If set, errors in this baseline are not reported.
+    pub baseline: Option<Baseline>,
+    /// If set, all the errors found are written to a baseline at this path instead of being reported.
+    pub write_baseline: Option<PathBuf>,
+    /// If true, a line explaining why is printed to stderr when the results count as a failure.
+    pub print_exit_reason: bool,
+    /// If true, files are linted one at a time in the order given, sharing a LinterContext so that
+    /// problems spanning several files can be found. Results aren't cached in this mode.
+    pub ordered: bool,
+    /// If set, errors are written to this file instead of stdout.
+    pub output: Option<PathBuf>,
+    /// If true, text output is grouped by file, with a header for each one.
+    pub group: bool,
+    /// If true, `-- file: <name>` comments in SQL read from stdin mark which file the statements
+    /// after them came from, and errors in them are reported against that file.
+    pub stdin_file_markers: bool,
+    /// If true, only the total number of errors found is printed, and the result always counts
+    /// as success.
+    pub count: bool,
+    /// If true, text output includes the explanation of each error's rule underneath it.
+    pub verbose_findings: bool,
+    /// If true, a line for each file saying whether it passed is printed to stderr at the end.
+    pub per_file_exit: bool,
+    /// If false, files are linted one at a time on the current thread. The results are in the
+    /// order of the files given either way.
+    pub parallel: bool,
+    /// If true, a breakdown of where the time went, with the slowest files, is printed to stderr
+    /// once the files are linted.
+    pub timing: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        return Options{
+            format: Format::Text,
+            fail_on: Severity::Warning,
+            quiet: false,
+            summary: true,
+            max_warnings: None,
+            color: false,
+            dedupe: false,
+            stdin_filename: None,
+            cache_dir: None,
+            baseline: None,
+            write_baseline: None,
+            print_exit_reason: false,
+            ordered: false,
+            output: None,
+            group: true,
+            stdin_file_markers: false,
+            count: false,
+            verbose_findings: false,
+            per_file_exit: false,
+            parallel: true,
+            timing: false,
+        };
+    }
+}
+
+/// The formats that we can print errors in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Sarif,
+    /// Workflow commands that GitHub Actions displays as annotations.
+    Github,
+    /// JUnit XML, as understood by many CI test reporters.
+    Junit,
+    /// Checkstyle XML, as understood by Jenkins and other CI plugins.
+    Checkstyle,
+    /// A JSON object per finding, one per line, which can be written out as files are linted.
+    Ndjson,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        return match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "sarif" => Ok(Format::Sarif),
+            "github" => Ok(Format::Github),
+            "junit" => Ok(Format::Junit),
+            "checkstyle" => Ok(Format::Checkstyle),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(format!("Unknown format {}", s)),
+        };
+    }
 }
 
+/// When to colour text output.
 #[derive(Debug, Clone, Copy, PartialEq)]
-enum ErrorCode {
+pub enum ColorChoice {
+    /// Only if stdout is a terminal and NO_COLOR isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorChoice, String> {
+        return match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("Unknown color choice {}", s)),
+        };
+    }
+}
+
+impl ColorChoice {
+    /// Returns true if output should be coloured.
+    pub fn enabled(&self) -> bool {
+        return match self {
+            ColorChoice::Auto => std::env::var_os("NO_COLOR").is_none() && atty::is(atty::Stream::Stdout),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        };
+    }
+}
+
+/// The SQL dialects that we can parse.
+/// Only PostgreSQL has any performance rules; the others are only checked for syntax errors.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    Postgres,
+    MySql,
+    Sqlite,
+    Generic,
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        return Dialect::Postgres;
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Dialect, String> {
+        return match s {
+            "postgres" => Ok(Dialect::Postgres),
+            "mysql" => Ok(Dialect::MySql),
+            "sqlite" => Ok(Dialect::Sqlite),
+            "generic" => Ok(Dialect::Generic),
+            _ => Err(format!("Unknown dialect {}", s)),
+        };
+    }
+}
+
+impl Dialect {
+    fn parser_dialect(&self) -> Box<dyn dialect::Dialect> {
+        return match self {
+            Dialect::Postgres => Box::new(dialect::PostgreSqlDialect{}),
+            Dialect::MySql => Box::new(dialect::MySqlDialect{}),
+            Dialect::Sqlite => Box::new(dialect::SQLiteDialect{}),
+            Dialect::Generic => Box::new(dialect::GenericDialect{}),
+        };
+    }
+}
+
+/// A major version of PostgreSQL. Some operations are only a problem on older versions, so rules
+/// change what they report depending on which one migrations are going to run against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct PgVersion(pub u32);
+
+impl PgVersion {
+    /// The version whose behaviour the rules follow if the config doesn't give one.
+    pub const LATEST: PgVersion = PgVersion(16);
+}
+
+impl fmt::Display for PgVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.0);
+    }
+}
+
+impl FromStr for PgVersion {
+    type Err = String;
+
+    /// Parses a version like "11", or "9.6" for the versions before 10 whose major version had
+    /// two parts; only the first part matters for our purposes.
+    fn from_str(s: &str) -> Result<PgVersion, String> {
+        return s.split('.').next().unwrap_or("").parse()
+            .map(PgVersion)
+            .map_err(|_| format!("Invalid PostgreSQL version {}", s));
+    }
+}
+
+impl<'de> Deserialize<'de> for PgVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<PgVersion, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Version {
+            Number(u32),
+            String(String),
+        }
+        return match Version::deserialize(deserializer)? {
+            Version::Number(version) => Ok(PgVersion(version)),
+            Version::String(version) => version.parse().map_err(serde::de::Error::custom),
+        };
+    }
+}
+
+/// Lint the given file and return any errors found, using the default configuration.
+/// If the path is "-", the SQL is read from stdin.
+pub fn lint_file(path: &Path) -> Vec<LintError> {
+    return lint_errors(path, &Config::default());
+}
+
+/// Lint the given SQL and return any errors found, using the default configuration.
+pub fn lint_str(sql: &str) -> Vec<LintError> {
+    return lint_with_config(sql, &Config::default());
+}
+
+/// Lint the given SQL and return any errors found, applying the given configuration.
+pub fn lint_with_config(sql: &str, config: &Config) -> Vec<LintError> {
+    return Linter::new().lint_str(sql, config);
+}
+
+/// Returns true if linting the given SQL with the given configuration finds anything at or above
+/// the given severity, i.e. if it would fail with that as fail_on (so disabled rules, small tables
+/// and severity overrides all apply as they do in lint_with_config). Statements are parsed and
+/// linted one at a time, stopping at the first such finding, so it's cheaper when only a yes or
+/// no is needed.
+pub fn has_findings(sql: &str, config: &Config, fail_on: Severity) -> bool {
+    let linter = Linter::new();
+    let mut context = LinterContext::new();
+    let dialect = config.dialect().parser_dialect();
+    let chunks = split_statements(sql);
+    let (suppressions, unknown_suppressions) = suppressions(sql, &chunks);
+    let statements = parse_chunks(chunks, &*dialect);
+    let errors = lint_parsed(&linter, statements, suppressions, config, &mut context).chain(unknown_suppressions);
+    return apply_config(errors, config).any(|e| e.severity >= fail_on);
+}
+
+/// How serious an error is.
+/// Config files name these in lowercase, but they're serialised as-is, which is also accepted
+/// so that serialised errors can be read back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum Severity {
+    /// Worth knowing about but not usually a problem.
+    #[serde(alias = "Info")]
+    Info,
+    /// Likely to cause performance problems or downtime, depending on the size of the table.
+    #[serde(alias = "Warning")]
+    Warning,
+    /// Will cause the migration to fail, or otherwise always a problem.
+    #[serde(alias = "Error")]
+    Error,
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Severity, String> {
+        return match s {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("Unknown severity {}", s)),
+        };
+    }
+}
+
+/// The kinds of error that we can report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum ErrorCode {
+    /// The file couldn't be read.
     FileError,
+    /// The file couldn't be parsed.
     SyntaxError,
+    /// A column is added with NOT NULL.
     NotNullColumn,
+    /// A column is added with a default value.
     DefaultValue,
+    /// An index is created without CONCURRENTLY.
     NonConcurrentIndex,
+    /// A column is renamed.
     RenameColumn,
+    /// A table is renamed.
     RenameTable,
+    /// A column's type is changed.
+    ColumnTypeChange,
+    /// An existing column is set to NOT NULL.
+    SetNotNull,
+    /// A column is dropped.
+    DropColumn,
+    /// A foreign key constraint is added without NOT VALID.
+    ForeignKeyNotValidated,
+    /// A CHECK constraint is added without NOT VALID.
+    CheckConstraintNotValidated,
+    /// An index is dropped without CONCURRENTLY.
+    NonConcurrentIndexDrop,
+    /// An index is created CONCURRENTLY inside a transaction block.
+    ConcurrentIndexInTransaction,
+    /// An UPDATE or DELETE has no WHERE clause.
+    UnboundedWrite,
+    /// A table is dropped or truncated.
+    DestructiveStatement,
+    /// A file takes locks without setting lock_timeout first.
+    MissingLockTimeout,
+    /// A column uses a SERIAL type rather than an identity column.
+    PreferIdentity,
+    /// A VARCHAR or TEXT column has no maximum length.
+    UnboundedVarchar,
+    /// CLUSTER, VACUUM FULL or REINDEX without CONCURRENTLY is run.
+    HeavyMaintenance,
+    /// A primary key is added to an existing table without using an existing index.
+    AddPrimaryKey,
+    /// An INSERT copies the results of an unlimited query into a table.
+    UnbatchedBackfill,
+    /// Several risky operations are combined in a single ALTER TABLE.
+    MultipleAlterOps,
+    /// An existing column's default is set to a volatile value.
+    VolatileSetDefault,
+    /// A stored generated column is added to an existing table.
+    AddGeneratedColumn,
+    /// A column is added with NOT NULL and a constant default.
+    NotNullWithDefault,
+    /// An index is created without a name.
+    UnnamedIndex,
+    /// A column is backfilled after it was made NOT NULL.
+    NotNullBeforeBackfill,
+    /// An index is created CONCURRENTLY, as it should be.
+    ConcurrentIndex,
+    /// An existing column's NOT NULL constraint is dropped.
+    DropNotNull,
+    /// A UNIQUE constraint is added to an existing table without using an existing index.
+    AddUniqueConstraint,
+    /// An index is created with a different method than the one configured for its columns.
+    IndexMethod,
+    /// A column is added with an inline foreign key reference.
+    InlineColumnForeignKey,
+    /// A statement that can't run inside a transaction block.
+    TransactionIncompatible,
+    /// A problem found by a rule added through the library API.
+    Custom,
+    /// A file has no statements in it.
+    EmptyMigration,
+    /// A statement that takes a table lock comes after a long-running write in the same file.
+    LockAfterLongWrite,
+    /// A column is added to an existing table with a default taken from a sequence.
+    AddColumnSequenceDefault,
+    /// An index is created with the same definition as another one in the same file.
+    DuplicateIndex,
+    /// A table or index is moved to another tablespace.
+    SetTablespace,
+    /// Data is bulk loaded into a table after a statement that locked it in the same file.
+    LoadAfterLock,
+    /// A NOT NULL column is added to an existing table with a volatile default.
+    UnsafeNotNullDefault,
+    /// An object is dropped without IF EXISTS.
+    DropWithoutIfExists,
+    /// A table is created from the results of a query.
+    CreateTableAsSelect,
+    /// Triggers or rules on a table are disabled, so they're no longer enforced.
+    DisabledIntegrity,
+    /// A materialized view is refreshed without CONCURRENTLY.
+    NonConcurrentRefresh,
+    /// A table that locking is a problem for is partitioned, so the lock is taken on every partition.
+    PartitionedTable,
+    /// A column is added to an existing table with an inline PRIMARY KEY.
+    InlinePrimaryKeyColumn,
+    /// A column's default contains a subquery or refers to a column.
+    ComplexDefault,
+    /// A suppression comment names a rule that doesn't exist.
+    UnknownSuppression,
 }
 
-#[derive(Debug, Clone)]
-struct LintError {
+impl ErrorCode {
+    /// Every code, in order.
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::FileError,
+        ErrorCode::SyntaxError,
+        ErrorCode::NotNullColumn,
+        ErrorCode::DefaultValue,
+        ErrorCode::NonConcurrentIndex,
+        ErrorCode::RenameColumn,
+        ErrorCode::RenameTable,
+        ErrorCode::ColumnTypeChange,
+        ErrorCode::SetNotNull,
+        ErrorCode::DropColumn,
+        ErrorCode::ForeignKeyNotValidated,
+        ErrorCode::CheckConstraintNotValidated,
+        ErrorCode::NonConcurrentIndexDrop,
+        ErrorCode::ConcurrentIndexInTransaction,
+        ErrorCode::UnboundedWrite,
+        ErrorCode::DestructiveStatement,
+        ErrorCode::MissingLockTimeout,
+        ErrorCode::PreferIdentity,
+        ErrorCode::UnboundedVarchar,
+        ErrorCode::HeavyMaintenance,
+        ErrorCode::AddPrimaryKey,
+        ErrorCode::UnbatchedBackfill,
+        ErrorCode::MultipleAlterOps,
+        ErrorCode::VolatileSetDefault,
+        ErrorCode::AddGeneratedColumn,
+        ErrorCode::NotNullWithDefault,
+        ErrorCode::UnnamedIndex,
+        ErrorCode::NotNullBeforeBackfill,
+        ErrorCode::ConcurrentIndex,
+        ErrorCode::DropNotNull,
+        ErrorCode::AddUniqueConstraint,
+        ErrorCode::IndexMethod,
+        ErrorCode::InlineColumnForeignKey,
+        ErrorCode::TransactionIncompatible,
+        ErrorCode::Custom,
+        ErrorCode::EmptyMigration,
+        ErrorCode::LockAfterLongWrite,
+        ErrorCode::AddColumnSequenceDefault,
+        ErrorCode::DuplicateIndex,
+        ErrorCode::SetTablespace,
+        ErrorCode::LoadAfterLock,
+        ErrorCode::UnsafeNotNullDefault,
+        ErrorCode::DropWithoutIfExists,
+        ErrorCode::CreateTableAsSelect,
+        ErrorCode::DisabledIntegrity,
+        ErrorCode::NonConcurrentRefresh,
+        ErrorCode::PartitionedTable,
+        ErrorCode::InlinePrimaryKeyColumn,
+        ErrorCode::ComplexDefault,
+        ErrorCode::UnknownSuppression,
+    ];
+
+    /// Returns the name of this code, as used in config files, suppression comments and output.
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            ErrorCode::FileError => "FileError",
+            ErrorCode::SyntaxError => "SyntaxError",
+            ErrorCode::NotNullColumn => "NotNullColumn",
+            ErrorCode::DefaultValue => "DefaultValue",
+            ErrorCode::NonConcurrentIndex => "NonConcurrentIndex",
+            ErrorCode::RenameColumn => "RenameColumn",
+            ErrorCode::RenameTable => "RenameTable",
+            ErrorCode::ColumnTypeChange => "ColumnTypeChange",
+            ErrorCode::SetNotNull => "SetNotNull",
+            ErrorCode::DropColumn => "DropColumn",
+            ErrorCode::ForeignKeyNotValidated => "ForeignKeyNotValidated",
+            ErrorCode::CheckConstraintNotValidated => "CheckConstraintNotValidated",
+            ErrorCode::NonConcurrentIndexDrop => "NonConcurrentIndexDrop",
+            ErrorCode::ConcurrentIndexInTransaction => "ConcurrentIndexInTransaction",
+            ErrorCode::UnboundedWrite => "UnboundedWrite",
+            ErrorCode::DestructiveStatement => "DestructiveStatement",
+            ErrorCode::MissingLockTimeout => "MissingLockTimeout",
+            ErrorCode::PreferIdentity => "PreferIdentity",
+            ErrorCode::UnboundedVarchar => "UnboundedVarchar",
+            ErrorCode::HeavyMaintenance => "HeavyMaintenance",
+            ErrorCode::AddPrimaryKey => "AddPrimaryKey",
+            ErrorCode::UnbatchedBackfill => "UnbatchedBackfill",
+            ErrorCode::MultipleAlterOps => "MultipleAlterOps",
+            ErrorCode::VolatileSetDefault => "VolatileSetDefault",
+            ErrorCode::AddGeneratedColumn => "AddGeneratedColumn",
+            ErrorCode::NotNullWithDefault => "NotNullWithDefault",
+            ErrorCode::UnnamedIndex => "UnnamedIndex",
+            ErrorCode::NotNullBeforeBackfill => "NotNullBeforeBackfill",
+            ErrorCode::ConcurrentIndex => "ConcurrentIndex",
+            ErrorCode::DropNotNull => "DropNotNull",
+            ErrorCode::AddUniqueConstraint => "AddUniqueConstraint",
+            ErrorCode::IndexMethod => "IndexMethod",
+            ErrorCode::InlineColumnForeignKey => "InlineColumnForeignKey",
+            ErrorCode::TransactionIncompatible => "TransactionIncompatible",
+            ErrorCode::Custom => "Custom",
+            ErrorCode::EmptyMigration => "EmptyMigration",
+            ErrorCode::LockAfterLongWrite => "LockAfterLongWrite",
+            ErrorCode::AddColumnSequenceDefault => "AddColumnSequenceDefault",
+            ErrorCode::DuplicateIndex => "DuplicateIndex",
+            ErrorCode::SetTablespace => "SetTablespace",
+            ErrorCode::LoadAfterLock => "LoadAfterLock",
+            ErrorCode::UnsafeNotNullDefault => "UnsafeNotNullDefault",
+            ErrorCode::DropWithoutIfExists => "DropWithoutIfExists",
+            ErrorCode::CreateTableAsSelect => "CreateTableAsSelect",
+            ErrorCode::DisabledIntegrity => "DisabledIntegrity",
+            ErrorCode::NonConcurrentRefresh => "NonConcurrentRefresh",
+            ErrorCode::PartitionedTable => "PartitionedTable",
+            ErrorCode::InlinePrimaryKeyColumn => "InlinePrimaryKeyColumn",
+            ErrorCode::ComplexDefault => "ComplexDefault",
+            ErrorCode::UnknownSuppression => "UnknownSuppression",
+        };
+    }
+
+    /// Returns the default severity of errors of this kind.
+    pub fn severity(&self) -> Severity {
+        return match self {
+            ErrorCode::FileError | ErrorCode::SyntaxError | ErrorCode::ConcurrentIndexInTransaction |
+            ErrorCode::UnsafeNotNullDefault | ErrorCode::PartitionedTable => Severity::Error,
+            ErrorCode::RenameTable | ErrorCode::VolatileSetDefault | ErrorCode::NotNullWithDefault |
+            ErrorCode::ConcurrentIndex | ErrorCode::DropNotNull | ErrorCode::EmptyMigration => Severity::Info,
+            _ => Severity::Warning,
+        };
+    }
+
+    /// Returns true if this rule is only a problem because of how long a table is locked for,
+    /// so doesn't matter for tables that are small enough.
+    pub fn is_lock_related(&self) -> bool {
+        return match self {
+            ErrorCode::NotNullColumn | ErrorCode::DefaultValue | ErrorCode::NonConcurrentIndex |
+            ErrorCode::ColumnTypeChange | ErrorCode::SetNotNull | ErrorCode::ForeignKeyNotValidated |
+            ErrorCode::CheckConstraintNotValidated | ErrorCode::NonConcurrentIndexDrop |
+            ErrorCode::AddPrimaryKey | ErrorCode::MultipleAlterOps | ErrorCode::AddGeneratedColumn |
+            ErrorCode::AddUniqueConstraint | ErrorCode::InlineColumnForeignKey | ErrorCode::AddColumnSequenceDefault |
+            ErrorCode::SetTablespace | ErrorCode::UnsafeNotNullDefault | ErrorCode::NonConcurrentRefresh |
+            ErrorCode::InlinePrimaryKeyColumn => true,
+            _ => false,
+        };
+    }
+
+    /// Returns true if this rule is enabled unless the config says otherwise.
+    /// Rules that are more opinionated are off by default.
+    pub fn enabled_by_default(&self) -> bool {
+        return match self {
+            ErrorCode::MissingLockTimeout | ErrorCode::PreferIdentity | ErrorCode::UnboundedVarchar | ErrorCode::UnbatchedBackfill |
+            ErrorCode::UnnamedIndex | ErrorCode::ConcurrentIndex | ErrorCode::IndexMethod |
+            ErrorCode::TransactionIncompatible | ErrorCode::EmptyMigration | ErrorCode::LoadAfterLock |
+            ErrorCode::DropWithoutIfExists | ErrorCode::CreateTableAsSelect | ErrorCode::PartitionedTable => false,
+            _ => true,
+        };
+    }
+
+    /// Returns true if this code means a file couldn't be linted at all, so it's always reported
+    /// whatever the config says; otherwise such files would pass without being checked.
+    pub fn always_enabled(&self) -> bool {
+        return *self == ErrorCode::FileError || *self == ErrorCode::SyntaxError;
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.write_str(self.as_str());
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+
+    /// Parses the name of a rule, ignoring case.
+    fn from_str(s: &str) -> Result<ErrorCode, String> {
+        return ErrorCode::ALL.iter()
+            .find(|code| code.as_str().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("Unknown rule {}, see --rules for the list of them", s));
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    /// Deserializes a rule name the same way as they're parsed on the command line.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<ErrorCode, D::Error> {
+        return String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom);
+    }
+}
+
+/// A single problem found by the linter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintError {
     code: ErrorCode,
+    severity: Severity,
     message: String,
+    line: usize,
+    column: usize,
+    /// The table the statement causing this error operates on, if there is one.
+    #[serde(skip)]
+    table: Option<String>,
+    /// The column the error is about, if it's about one. It's positioned at that column's
+    /// definition in the statement (or wherever else it's named) if we can find it.
+    #[serde(skip)]
+    column_name: Option<String>,
 }
 
 impl PartialEq for LintError {
     // Ignore the details of the message for the purpose of comparison.
     fn eq(&self, other: &LintError) -> bool {
-        return self.code == other.code;
+        return self.code == other.code && self.line == other.line && self.column == other.column;
     }
 }
 
 impl LintError {
-    /// Create a new error
+    /// Create a new error. It is positioned at the start of the file until at() is called.
     pub fn new(code: ErrorCode, message: &str) -> LintError {
-        return LintError{code: code, message: message.to_string()};
+        return LintError{code: code, severity: code.severity(), message: message.to_string(), line: 1, column: 1, table: None, column_name: None};
+    }
+
+    /// Returns this error positioned at the given line and column.
+    pub fn at(self, line: usize, column: usize) -> LintError {
+        return LintError{line: line, column: column, ..self};
+    }
+
+    /// Returns this error attributed to the given table.
+    fn on_table(self, table: &ast::ObjectName) -> LintError {
+        return LintError{table: Some(table.to_string()), ..self};
+    }
+
+    /// Returns this error attributed to the given column.
+    fn on_column(self, column: &ast::Ident) -> LintError {
+        return LintError{column_name: Some(column.to_string()), ..self};
+    }
+
+    /// Returns the kind of this error.
+    pub fn code(&self) -> ErrorCode {
+        return self.code;
+    }
+
+    /// Returns how serious this error is.
+    pub fn severity(&self) -> Severity {
+        return self.severity;
+    }
+
+    /// Returns the human-readable description of this error.
+    pub fn message(&self) -> &str {
+        return &self.message;
+    }
+
+    /// Returns the line (1-based) at which this error occurs.
+    pub fn line(&self) -> usize {
+        return self.line;
+    }
+
+    /// Returns the column (1-based) at which this error occurs.
+    pub fn column(&self) -> usize {
+        return self.column;
+    }
+
+    /// Returns the table that the statement causing this error operates on, if known.
+    pub fn table(&self) -> Option<&str> {
+        return self.table.as_deref();
     }
 }
 
-fn lint_one(file: &PathBuf) -> bool {
-    debug!("Linting {}...", file.as_path().to_string_lossy());
-    let errors = lint_errors(file);
-    errors.iter().for_each(|e| {
-        println!("{}:{:?}:{}", file.as_path().to_string_lossy(), e.code, e.message);
-    });
-    errors.is_empty()
+/// Writes the results in the format the options ask for.
+fn write_report(out: &mut dyn Write, results: &[FileResult], options: &Options) -> io::Result<()> {
+    match options.format {
+        Format::Text => write_text(out, results, options)?,
+        Format::Json => write_json(out, results)?,
+        Format::Sarif => write_sarif(out, results)?,
+        Format::Github => write_github(out, results)?,
+        Format::Junit => write!(out, "{}", junit::report(results))?,
+        Format::Checkstyle => write!(out, "{}", checkstyle::report(results))?,
+        Format::Ndjson => write_ndjson(out, results)?,
+    }
+    return out.flush();
+}
+
+/// Writes the results as text, one line per error. If grouping, each file with errors in it gets
+/// a header line, and a blank line after its errors. In verbose mode each error is followed by
+/// the explanation of its rule, indented.
+fn write_text(out: &mut dyn Write, results: &[FileResult], options: &Options) -> io::Result<()> {
+    for FileResult{path: name, errors} in results.iter().filter(|result| !result.errors.is_empty()) {
+        if options.group {
+            let header = format!("{} ({})", name, plural(errors.len(), "issue"));
+            writeln!(out, "{}", if options.color { header.bold().to_string() } else { header })?;
+        }
+        for e in errors {
+            writeln!(out, "{}", text_line(name, e, options.color))?;
+            if options.verbose_findings {
+                for line in rule(e.code).explanation.lines() {
+                    if line.is_empty() {
+                        writeln!(out)?;
+                    } else {
+                        writeln!(out, "    {}", line)?;
+                    }
+                }
+            }
+        }
+        if options.group {
+            writeln!(out)?;
+        }
+    }
+    return Ok(());
+}
+
+/// Formats an error as a line of text output, optionally coloured by severity.
+fn text_line(name: &str, e: &LintError, color: bool) -> String {
+    let location = format!("{}:{}:{}", name, e.line, e.column);
+    let code = format!("{:?}:{}", e.severity, e.code);
+    if !color {
+        return format!("{}:{}:{}", location, code, e.message);
+    }
+    let code = match e.severity {
+        Severity::Error => code.red().to_string(),
+        Severity::Warning => code.yellow().to_string(),
+        Severity::Info => code.blue().to_string(),
+    };
+    return format!("{}:{}:{}", location.dimmed(), code, e.message);
+}
+
+/// Returns a one-line summary of the number of errors found, broken down by code with the most
+/// common first, or None if there weren't any.
+fn summary(results: &[FileResult]) -> Option<String> {
+    let mut counts: Vec<(ErrorCode, usize)> = Vec::new();
+    for e in results.iter().flat_map(|result| &result.errors) {
+        match counts.iter_mut().find(|(code, _)| *code == e.code) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((e.code, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1));
+    let errors = counts.iter().map(|(_, count)| count).sum::<usize>();
+    let files = results.iter().filter(|result| !result.errors.is_empty()).count();
+    return if errors == 0 {
+        None
+    } else {
+        let breakdown = counts.iter().map(|(code, count)| format!("{}: {}", code, count)).collect::<Vec<_>>();
+        Some(format!("{} in {} ({})", plural(errors, "issue"), plural(files, "file"), breakdown.join(", ")))
+    };
+}
+
+fn plural(n: usize, word: &str) -> String {
+    return if n == 1 {
+        format!("{} {}", n, word)
+    } else {
+        format!("{} {}s", n, word)
+    };
+}
+
+/// Returns true if none of the given errors are at or above the given severity.
+fn succeeded(errors: &[LintError], fail_on: Severity) -> bool {
+    return errors.iter().all(|e| e.severity < fail_on);
+}
+
+/// The top-level document of JSON output.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonReport<'a> {
+    tool: &'static str,
+    tool_version: &'static str,
+    findings: Vec<Finding<'a>>,
+}
+
+/// A single error in a file, as it is serialised in JSON output.
+#[derive(Serialize)]
+struct Finding<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    error: &'a LintError,
+}
+
+fn write_json(out: &mut dyn Write, results: &[FileResult]) -> io::Result<()> {
+    let findings = results.iter().flat_map(|result| {
+        result.errors.iter().map(move |e| Finding{file: &result.path, error: e})
+    }).collect::<Vec<_>>();
+    let report = JsonReport{tool: env!("CARGO_PKG_NAME"), tool_version: VERSION, findings: findings};
+    return writeln!(out, "{}", serde_json::to_string_pretty(&report).unwrap());
+}
+
+/// Writes each finding as a JSON object on a line of its own.
+fn write_ndjson(out: &mut dyn Write, results: &[FileResult]) -> io::Result<()> {
+    for result in results {
+        for e in &result.errors {
+            writeln!(out, "{}", serde_json::to_string(&Finding{file: &result.path, error: e}).unwrap())?;
+        }
+    }
+    return Ok(());
+}
+
+fn write_sarif(out: &mut dyn Write, results: &[FileResult]) -> io::Result<()> {
+    return writeln!(out, "{}", serde_json::to_string_pretty(&sarif::Log::new(results)).unwrap());
+}
+
+fn write_github(out: &mut dyn Write, results: &[FileResult]) -> io::Result<()> {
+    for result in results {
+        for e in &result.errors {
+            writeln!(out, "{}", github_annotation(&result.path, e))?;
+        }
+    }
+    return Ok(());
+}
+
+/// Formats an error as a GitHub Actions workflow command.
+fn github_annotation(name: &str, e: &LintError) -> String {
+    let command = match e.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "notice",
+    };
+    return format!("::{} file={},line={},col={},title={}::{}", command,
+                   github_escape(name, true), e.line, e.column, e.code, github_escape(&e.message, false));
+}
+
+/// Escapes a string for use in a workflow command; properties need more escaping than the message.
+fn github_escape(s: &str, property: bool) -> String {
+    let s = s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+    return if property {
+        s.replace(':', "%3A").replace(',', "%2C")
+    } else {
+        s
+    };
+}
+
+fn is_stdin(file: &Path) -> bool {
+    return file.as_os_str() == "-";
+}
+
+/// Returns the name to report errors in the given file under. Stdin is reported as the given
+/// name, or "<stdin>" if there isn't one.
+fn display_name(file: &Path, stdin_name: Option<&str>) -> String {
+    return if is_stdin(file) {
+        stdin_name.unwrap_or("<stdin>").to_string()
+    } else {
+        file.to_string_lossy().to_string()
+    }
+}
+
+/// Reads the contents of the given file, or of stdin if it is "-".
+fn read_contents(file: &Path) -> io::Result<String> {
+    if is_stdin(file) {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        return Ok(contents);
+    }
+    return fs::read_to_string(file);
+}
+
+/// Lints SQL read from stdin that contains file markers, returning the errors for each file they
+/// name. Anything before the first marker is reported under the given name.
+fn lint_marked_stdin(linter: &Linter, name: String, config: &Config, context: &mut LinterContext) -> Vec<FileResult> {
+    let contents = match read_contents(Path::new("-")) {
+        Err(e) => return vec![FileResult{path: name, errors: vec![LintError::new(ErrorCode::FileError, &e.to_string())]}],
+        Ok(contents) => contents,
+    };
+    let markers = file_markers(&contents);
+    let errors = lint_contents(linter, contents, config, context);
+    return split_by_markers(name, &markers, errors);
+}
+
+fn lint_errors(file: &Path, config: &Config) -> Vec<LintError> {
+    return Linter::new().lint_file(file, config);
 }
 
-fn lint_errors(file: &PathBuf) -> Vec<LintError> {
-    let contents = match fs::read_to_string(file.as_path()) {
+/// As lint_errors, but carrying over the given context from files linted before this one.
+fn lint_errors_in(linter: &Linter, file: &Path, config: &Config, context: &mut LinterContext) -> Vec<LintError> {
+    return match read_sql(file, config) {
+        Err(e) => vec![LintError::new(ErrorCode::FileError, &e.to_string())],
+        Ok(contents) => lint_contents(linter, contents, config, context),
+    };
+}
+
+/// As lint_errors, but results are looked up in the cache first and stored there if they weren't.
+fn lint_errors_cached(linter: &Linter, file: &Path, config: &Config, cache: &Cache, context: &mut LinterContext) -> Vec<LintError> {
+    let contents = match read_sql(file, config) {
         Err(e) => return vec![LintError::new(ErrorCode::FileError, &e.to_string())],
         Ok(contents) => contents,
     };
+    let key = Cache::key(&contents, config);
+    if let Some(errors) = cache.get(&key) {
+        debug!("Using cached results for {}", display_name(file, None));
+        return errors;
+    }
+    let errors = lint_contents(linter, contents, config, context);
+    cache.put(&key, &errors);
+    return errors;
+}
+
+/// Reads the SQL to lint from the given file. If the config says so, the SQL embedded in
+/// migrations written in other languages is extracted from them, on the lines it was found on.
+fn read_sql(file: &Path, config: &Config) -> io::Result<String> {
+    let contents = read_contents(file)?;
+    return Ok(if config.embedded_sql() && extract::is_embedded(file) {
+        extract::embedded_sql(file, &contents)
+    } else {
+        contents
+    });
+}
+
+/// Lints the contents of a file, omitting any errors that are disabled by the config (or that
+/// are about locking tables it says are small) and applying any overrides it has for the rest.
+fn lint_contents(linter: &Linter, contents: String, config: &Config, context: &mut LinterContext) -> Vec<LintError> {
+    return lint_contents_iter(linter, contents, config, context).collect();
+}
+
+/// As lint_contents, but statements are only linted as the errors are needed.
+fn lint_contents_iter<'a>(linter: &'a Linter, contents: String, config: &'a Config, context: &'a mut LinterContext) -> impl Iterator<Item = LintError> + 'a {
+    return apply_config(lint_sql_iter(linter, contents, config, context), config);
+}
+
+/// Omits any of the given errors that are disabled by the config (or that are about locking
+/// tables it says are small) and applies any overrides it has to the rest.
+fn apply_config<'a>(errors: impl Iterator<Item = LintError> + 'a, config: &'a Config) -> impl Iterator<Item = LintError> + 'a {
+    return errors
+        .filter(move |e| config.is_enabled(e.code))
+        .filter(move |e| !(e.code.is_lock_related() && e.table.as_deref().map_or(false, |t| config.is_small_table(t))))
+        .map(move |e| config.apply(e));
+}
+
+/// Raises the severity of an error about locking a table if the table is partitioned (either
+/// because the config says so, or because it was created with PARTITION BY), since then the lock
+/// is taken on every partition. This only happens if the PartitionedTable rule is enabled.
+fn on_partitioned_table(error: LintError, config: &Config, context: &LinterContext) -> LintError {
+    if !error.code.is_lock_related() || !config.is_enabled(ErrorCode::PartitionedTable) {
+        return error;
+    }
+    let table = match error.table.as_deref() {
+        Some(table) if config.is_partitioned_table(table) || context.is_partitioned_table(table) => table.to_string(),
+        _ => return error,
+    };
+    let severity = error.severity.max(ErrorCode::PartitionedTable.severity());
+    let message = format!("{} {} is partitioned, so this applies to every one of its partitions.", error.message, table);
+    return LintError{severity: severity, message: message, ..error};
+}
+
+/// Lints a string containing SQL statements in the configured dialect, with the given checks.
+fn lint_sql(linter: &Linter, contents: String, config: &Config, context: &mut LinterContext) -> Vec<LintError> {
+    return lint_sql_iter(linter, contents, config, context).collect();
+}
+
+/// As lint_sql, but statements are only linted as the errors are needed. They're all parsed
+/// up front though, since that's how we find out where each one starts.
+fn lint_sql_iter<'a>(linter: &'a Linter, contents: String, config: &'a Config, context: &'a mut LinterContext) -> impl Iterator<Item = LintError> + 'a {
+    let dialect = config.dialect();
+    let start = Instant::now();
+    let chunks = split_statements(&contents);
+    let (suppressions, unknown_suppressions) = suppressions(&contents, &chunks);
+    let statements = parse(contents, chunks, &*dialect.parser_dialect());
+    context.add_parse_time(start.elapsed());
+    return lint_parsed(linter, statements.into_iter(), suppressions, config, context).chain(unknown_suppressions);
+}
+
+/// Lints statements that have been parsed (or that sqlparser failed to parse), each with the chunk
+/// of SQL it came from, given the suppression comments in the file they came from. This is
+/// what all the ways of linting SQL come down to; the errors aren't filtered by the config yet.
+/// Statements are only taken from the iterator as the errors are needed.
+fn lint_parsed<'a>(linter: &'a Linter, statements: impl Iterator<Item = (Chunk, Result<ast::Statement, Unparsed>)> + 'a,
+                   suppressions: Vec<Suppression>, config: &'a Config, context: &'a mut LinterContext) -> impl Iterator<Item = LintError> + 'a {
+    let dialect = config.dialect();
+    let mut file = FileLinter::new(linter, config, context);
+    let mut previous_line = 0;
+    // The None at the end is where the rules get to look at the file as a whole.
+    return statements.map(Some).chain(std::iter::once(None)).flat_map(move |stmt| {
+        let (chunk, stmt) = match stmt {
+            Some(stmt) => stmt,
+            None => return file.finish(),
+        };
+        match &stmt {
+            Ok(ast::Statement::StartTransaction{..}) | Ok(ast::Statement::Commit{..}) | Ok(ast::Statement::Rollback{..}) => (),
+            _ => file.state.has_statements = true,
+        }
+        let (line, column) = (chunk.line, chunk.column);
+        let start = Instant::now();
+        // These work on the SQL, so they can apply to statements sqlparser can't parse.
+        let by_sql = match &stmt {
+            _ if dialect != Dialect::Postgres => Vec::new(),
+            Ok(stmt) => file.lint_raw(&stmt.to_string(), line),
+            Err(unparsed) => file.lint_raw(&unparsed.sql, line),
+        };
+        let errors = match stmt {
+            Err(unparsed) if dialect == Dialect::Postgres => file.lint_unparsed(&unparsed.sql, line)
+                .or_else(|| lint_combined_alter_table(&unparsed.sql, line, &mut file))
+                .unwrap_or_else(|| vec![syntax_error(&unparsed)]),
+            Err(unparsed) => vec![syntax_error(&unparsed)],
+            Ok(_) if dialect != Dialect::Postgres => Vec::new(),
+            Ok(stmt) => file.lint(&stmt, line),
+        };
+        let errors = [errors, by_sql].concat();
+        // Suppressions apply to the statement following them. Ones inside the previous statement have already
+        // been dropped, so anything after its start is between the two.
+        let suppressions = suppressions.iter().filter(|s| s.line > previous_line && s.line < line).collect::<Vec<_>>();
+        previous_line = line;
+        let errors = errors.into_iter()
+            .filter(|e| !suppressions.iter().any(|s| s.suppresses(e.code)))
+            .map(|e| {
+                // Errors about a column are positioned at it, rather than the start of the statement.
+                let (line, column) = match e.column_name.as_deref().and_then(|name| column_position(&chunk.sql, name)) {
+                    Some((1, c)) => (line, column + c - 1),
+                    Some((l, c)) => (line + l - 1, c),
+                    None => (line, column),
+                };
+                on_partitioned_table(e, config, file.context).at(line, column)
+            })
+            .collect::<Vec<_>>();
+        file.context.add_rule_time(start.elapsed());
+        errors
+    });
+}
+
+/// Lints statements that have already been parsed, using the default configuration.
+/// There's no source text to refer to, so all the errors are positioned at the start.
+pub fn lint_statements(stmts: &[ast::Statement]) -> Vec<LintError> {
+    return LinterContext::new().lint_statements(stmts, &Config::default());
+}
+
+/// Lints statements that have already been parsed in the same way as lint_contents, carrying
+/// over the given context. All the errors are positioned at the start.
+fn lint_statements_in(stmts: &[ast::Statement], config: &Config, context: &mut LinterContext) -> Vec<LintError> {
+    let linter = Linter::new();
+    let statements = stmts.iter().map(|stmt| (Chunk{line: 1, column: 1, sql: String::new()}, Ok(stmt.clone())));
+    return apply_config(lint_parsed(&linter, statements, Vec::new(), config, context), config).collect();
+}
+
+/// Runs a linter's rules over the statements in a single file, keeping track of what they need
+/// to know about the statements before, along with the context of any files linted before it.
+#[derive(Debug)]
+struct FileLinter<'a> {
+    linter: &'a Linter,
+    config: &'a Config,
+    context: &'a mut LinterContext,
+    state: FileState,
+}
+
+impl<'a> FileLinter<'a> {
+    fn new(linter: &'a Linter, config: &'a Config, context: &'a mut LinterContext) -> FileLinter<'a> {
+        return FileLinter{linter: linter, config: config, context: context, state: FileState::new()};
+    }
+
+    /// Lints the next statement in the file, which starts on the given line.
+    fn lint(&mut self, stmt: &ast::Statement, line: usize) -> Vec<LintError> {
+        let errors = self.linter.check(stmt, &mut CheckContext{config: self.config, context: self.context, line: line, file: &mut self.state});
+        self.context.record(stmt);
+        return errors;
+    }
+
+    /// Lints the SQL of the next statement in the file, which starts on the given line.
+    fn lint_raw(&mut self, sql: &str, line: usize) -> Vec<LintError> {
+        self.record_partitioned_table(sql);
+        return self.linter.check_sql(sql, &mut CheckContext{config: self.config, context: self.context, line: line, file: &mut self.state});
+    }
+
+    /// Lints the next statement in the file, which sqlparser couldn't parse. Returns None if none
+    /// of the rules recognised it.
+    fn lint_unparsed(&mut self, sql: &str, line: usize) -> Option<Vec<LintError>> {
+        return self.linter.check_unparsed(sql, &mut CheckContext{config: self.config, context: self.context, line: line, file: &mut self.state});
+    }
+
+    /// Lints the file as a whole, once all its statements have been linted.
+    fn finish(&mut self) -> Vec<LintError> {
+        return self.linter.finish(&mut CheckContext{config: self.config, context: self.context, line: 1, file: &mut self.state});
+    }
+
+    /// Records the table created by a CREATE TABLE ... PARTITION BY statement as being partitioned.
+    /// sqlparser doesn't support PARTITION BY, so this works on the SQL.
+    fn record_partitioned_table(&mut self, sql: &str) {
+        let words = words(sql);
+        let words = words.iter().map(|w| w.as_str()).collect::<Vec<_>>();
+        match words.as_slice() {
+            ["CREATE", rest @ ..] if is_create_partitioned_table(rest) => (),
+            _ => return,
+        }
+        let tokens = index_tokens(sql);
+        let name = tokens.iter()
+            .skip_while(|t| !t.eq_ignore_ascii_case("TABLE"))
+            .skip(1)
+            .skip_while(|t| t.eq_ignore_ascii_case("IF") || t.eq_ignore_ascii_case("NOT") || t.eq_ignore_ascii_case("EXISTS"))
+            .take_while(|t| *t != "(")
+            .map(|t| t.as_str())
+            .collect::<Vec<_>>()
+            .join(".");
+        self.context.add_partitioned_table(&name);
+    }
+}
+
+/// What the built-in rules track across the statements in a single file.
+#[derive(Debug)]
+struct FileState {
+    in_transaction: bool,
+    lock_timeout: LockTimeout,
+    /// A description of the last long-running write, if no lock has been taken since.
+    long_write: Option<String>,
+    /// The indexes created so far, with the line each was created on.
+    indexes: Vec<(IndexDefinition, usize)>,
+    /// Tables locked by statements since the last COMMIT, with a description of what locked them.
+    locked_tables: Vec<(String, String)>,
+    /// Whether there have been any statements other than starting or ending a transaction.
+    has_statements: bool,
+}
+
+impl FileState {
+    fn new() -> FileState {
+        return FileState{
+            in_transaction: false,
+            lock_timeout: LockTimeout::Unset,
+            long_write: None,
+            indexes: Vec::new(),
+            locked_tables: Vec::new(),
+            has_statements: false,
+        };
+    }
+}
+
+/// A statement that sqlparser couldn't parse.
+#[derive(Debug)]
+struct Unparsed {
+    /// The error from the parser.
+    error: String,
+    /// The raw SQL of the statement.
+    sql: String,
+}
+
+/// The maximum length of the snippet of SQL quoted in a syntax error.
+const SNIPPET_LENGTH: usize = 60;
+
+/// Returns a syntax error for a statement that couldn't be parsed, quoting the start of it so
+/// it's easy to find which one it was in a file with many statements.
+fn syntax_error(unparsed: &Unparsed) -> LintError {
+    let sql = unparsed.sql.trim();
+    let first_line = sql.lines().next().unwrap_or("").trim();
+    let mut snippet = first_line.chars().take(SNIPPET_LENGTH).collect::<String>();
+    if snippet.len() < sql.len() {
+        snippet.push_str("...");
+    }
+    return LintError::new(ErrorCode::SyntaxError, format!("{} in statement: {}", unparsed.error, snippet).as_str());
+}
+
+/// Splits SQL into its words, uppercased so they can be matched against keywords.
+fn words(sql: &str) -> Vec<String> {
+    return sql.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_uppercase())
+        .collect();
+}
+
+/// sqlparser only supports a single operation per ALTER TABLE, so statements that combine several
+/// fail to parse. We split them up and lint each operation as if it were its own statement, then
+/// warn if more than one of them was risky, since they all happen under the same lock.
+/// Returns None if the SQL isn't an ALTER TABLE that can be split up like that.
+fn lint_combined_alter_table(sql: &str, line: usize, file: &mut FileLinter) -> Option<Vec<LintError>> {
+    let parts = split_operations(sql);
+    if parts.len() < 2 {
+        return None;
+    }
     let dialect = dialect::PostgreSqlDialect{};
-    let ast = match Parser::parse_sql(&dialect, contents) {
-        Err(e) => return vec![LintError::new(ErrorCode::SyntaxError, &e.to_string())],
-        Ok(ast) => ast,
+    let mut stmts = Parser::parse_sql(&dialect, parts[0].clone()).ok()?;
+    let name = match stmts.as_slice() {
+        [ast::Statement::AlterTable{name, ..}] => name.clone(),
+        _ => return None,
     };
-    return ast.iter().map(|stmt| lint_statement(stmt)).collect::<Vec<_>>().concat();
+    for part in &parts[1..] {
+        match Parser::parse_sql(&dialect, format!("ALTER TABLE {} {}", name, part)).ok()?.pop() {
+            Some(stmt @ ast::Statement::AlterTable{..}) => stmts.push(stmt),
+            _ => return None,
+        }
+    }
+    // Only operations that hold the lock for a long time make combining them risky.
+    let risky = stmts.iter().filter(|stmt| match stmt {
+        ast::Statement::AlterTable{name, operation} => lint_alter_table(name, operation, file.config.pg_version()).iter()
+            .any(|e| e.code.is_lock_related() && file.config.is_enabled(e.code)),
+        _ => false,
+    }).count();
+    let mut errors = stmts.iter().flat_map(|stmt| file.lint(stmt, line)).collect::<Vec<_>>();
+    if risky > 1 {
+        errors.push(LintError::new(ErrorCode::MultipleAlterOps, format!(
+            "ALTER TABLE {} combines {} risky operations. They all run under a single lock held until the last one finishes, and if any of them fails they are all rolled back; consider running them as separate statements.", name, risky).as_str()).on_table(&name));
+    }
+    return Some(errors);
+}
+
+/// Splits a statement on its top-level commas, i.e. ignoring any that are in parentheses,
+/// strings or comments.
+fn split_operations(sql: &str) -> Vec<String> {
+    let chars = sql.chars().collect::<Vec<_>>();
+    let mut parts = vec![String::new()];
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let len = token_length(&chars[i..]);
+        match chars[i] {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(String::new());
+                i += 1;
+                continue;
+            },
+            _ if is_comment(&chars[i..]) => {
+                parts.last_mut().unwrap().push(' ');
+                i += len;
+                continue;
+            },
+            _ => (),
+        }
+        parts.last_mut().unwrap().extend(&chars[i..i + len]);
+        i += len;
+    }
+    return parts.into_iter().map(|part| part.trim().to_string()).collect();
+}
+
+/// Parses the given SQL, already split into the given chunks, into statements along with the
+/// chunk each one came from. If it fails to parse as a whole, we fall back to parsing each
+/// statement separately, so a syntax error in one doesn't stop us linting the others.
+fn parse(contents: String, chunks: Vec<Chunk>, dialect: &dyn dialect::Dialect) -> Vec<(Chunk, Result<ast::Statement, Unparsed>)> {
+    if let Ok(ast) = Parser::parse_sql(dialect, contents) {
+        // If the statements don't line up with the chunks their positions are only a guess, so
+        // don't go looking for columns in the SQL of the wrong statement.
+        let matched = ast.len() == chunks.len();
+        let mut chunks = chunks.into_iter();
+        return ast.into_iter().map(|stmt| match chunks.next() {
+            Some(chunk) if matched => (chunk, Ok(stmt)),
+            Some(chunk) => (Chunk{sql: String::new(), ..chunk}, Ok(stmt)),
+            None => (Chunk{line: 1, column: 1, sql: String::new()}, Ok(stmt)),
+        }).collect();
+    }
+    return parse_chunks(chunks, dialect).collect();
+}
+
+/// Parses each of the given chunks separately, as they're needed.
+fn parse_chunks<'a>(chunks: Vec<Chunk>, dialect: &'a dyn dialect::Dialect) -> impl Iterator<Item = (Chunk, Result<ast::Statement, Unparsed>)> + 'a {
+    return chunks.into_iter().flat_map(move |chunk| match Parser::parse_sql(dialect, chunk.sql.clone()) {
+        Err(e) => {
+            let sql = chunk.sql.clone();
+            vec![(chunk, Err(Unparsed{error: e.to_string(), sql: sql}))]
+        },
+        Ok(ast) => ast.into_iter().map(|stmt| (chunk.clone(), Ok(stmt))).collect(),
+    });
+}
+
+/// The prefix of a comment that suppresses errors, e.g.
+///     -- sql-perf-linter: disable=NonConcurrentIndex,DefaultValue
+///     -- sql-perf-linter: disable-all
+const SUPPRESSION_PREFIX: &str = "-- sql-perf-linter:";
+
+/// A comment directive that suppresses errors for the statement following it.
+#[derive(Debug, PartialEq)]
+struct Suppression {
+    line: usize,
+    /// The codes that are suppressed, or None if they all are.
+    codes: Option<Vec<ErrorCode>>,
+}
+
+impl Suppression {
+    fn suppresses(&self, code: ErrorCode) -> bool {
+        return match &self.codes {
+            None => true,
+            Some(codes) => codes.contains(&code),
+        };
+    }
+}
+
+/// Finds all the suppression comments in the given SQL, which has been split into the given
+/// statements. sqlparser discards comments so we have to scan the raw text for these. Comments
+/// inside a statement are ignored, since they come before the end of it rather than the next one.
+/// Rule names are matched ignoring case; any that don't exist are returned as errors, since a typo
+/// would otherwise silently suppress nothing.
+fn suppressions(sql: &str, chunks: &[Chunk]) -> (Vec<Suppression>, Vec<LintError>) {
+    let end_line = |chunk: &Chunk| chunk.line + chunk.sql.matches('\n').count();
+    let mut next = 0;
+    let mut unknown = Vec::new();
+    let suppressions = sql.lines().enumerate().filter_map(|(i, line)| {
+        // Skip past the statements that end before this line; the next one contains it if it started before it.
+        while next < chunks.len() && end_line(&chunks[next]) < i + 1 {
+            next += 1;
+        }
+        if chunks.get(next).map_or(false, |chunk| chunk.line < i + 1) {
+            return None;
+        }
+        let directive = line.trim().strip_prefix(SUPPRESSION_PREFIX)?.trim();
+        let codes = if directive == "disable-all" {
+            None
+        } else {
+            let column = line.len() - line.trim_start().len() + 1;
+            Some(directive.strip_prefix("disable=")?.split(',').map(|c| c.trim()).filter_map(|c| match c.parse() {
+                Ok(code) => Some(code),
+                Err(_) => {
+                    unknown.push(LintError::new(ErrorCode::UnknownSuppression, format!(
+                        "Suppression comment names unknown rule {}, so it doesn't suppress anything. See --rules for the list of them.", c).as_str()).at(i + 1, column));
+                    None
+                },
+            }).collect())
+        };
+        Some(Suppression{line: i + 1, codes: codes})
+    }).collect();
+    return (suppressions, unknown);
+}
+
+/// The prefix of a comment marking which file the statements after it came from, when several
+/// files are concatenated together on stdin.
+const FILE_MARKER_PREFIX: &str = "-- file:";
+
+/// A comment marking the start of another file's statements.
+#[derive(Debug, PartialEq)]
+struct FileMarker {
+    line: usize,
+    file: String,
+}
+
+/// Finds all the file marker comments in the given SQL.
+fn file_markers(sql: &str) -> Vec<FileMarker> {
+    return sql.lines().enumerate().filter_map(|(i, line)| {
+        let file = line.trim().strip_prefix(FILE_MARKER_PREFIX)?.trim();
+        if file.is_empty() {
+            return None;
+        }
+        Some(FileMarker{line: i + 1, file: file.to_string()})
+    }).collect();
+}
+
+/// Splits errors between the files that the markers say they came from, renumbering their lines
+/// so they're relative to the marker, i.e. refer to the original file. Every marked file is
+/// included even if it has no errors; the part before the first marker is only included if it does.
+fn split_by_markers(name: String, markers: &[FileMarker], errors: Vec<LintError>) -> Vec<FileResult> {
+    let mut results = vec![FileResult{path: name, errors: Vec::new()}];
+    results.extend(markers.iter().map(|marker| FileResult::new(&marker.file, Vec::new())));
+    for e in errors {
+        match markers.iter().rposition(|marker| marker.line < e.line) {
+            Some(i) => results[i + 1].errors.push(LintError{line: e.line - markers[i].line, ..e}),
+            None => results[0].errors.push(e),
+        }
+    }
+    if !markers.is_empty() && results[0].errors.is_empty() {
+        results.remove(0);
+    }
+    return results;
+}
+
+/// The raw SQL of a single statement, along with the line and column (both 1-based) it starts at.
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    line: usize,
+    column: usize,
+    sql: String,
+}
+
+/// Splits the given SQL into its individual statements.
+/// sqlparser doesn't give us any source positions, so we find them by scanning for the
+/// semicolons between statements, taking care to skip over comments and quoted strings.
+fn split_statements(sql: &str) -> Vec<Chunk> {
+    let chars = sql.chars().collect::<Vec<_>>();
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut in_statement = false;
+    let mut line = 1;
+    let mut column = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let len = token_length(&chars[i..]);
+        if chars[i] == ';' {
+            in_statement = false;
+        } else if !in_statement && !chars[i].is_whitespace() && !is_comment(&chars[i..]) {
+            in_statement = true;
+            chunks.push(Chunk{line: line, column: column, sql: String::new()});
+        }
+        if in_statement {
+            chunks.last_mut().unwrap().sql.extend(&chars[i..i + len]);
+        }
+        for c in &chars[i..i + len] {
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        i += len;
+    }
+    return chunks;
 }
 
-fn lint_statement(stmt: &ast::Statement) -> Vec<LintError> {
-    return match stmt {
-        ast::Statement::AlterTable{name, operation} => lint_alter_table(name, operation),
-        ast::Statement::CreateIndex{name, concurrently, ..} => lint_create_index(name, *concurrently),
-        _ => Vec::new(),
+/// Returns true if the given (non-empty) input begins with a comment.
+fn is_comment(chars: &[char]) -> bool {
+    return match (chars[0], chars.get(1)) {
+        ('-', Some('-')) | ('/', Some('*')) => true,
+        _ => false,
     };
 }
 
-fn lint_alter_table(name: &ast::ObjectName, operation: &ast::AlterTableOperation) -> Vec<LintError> {
-    return match operation {
-        ast::AlterTableOperation::AddColumn(def) => lint_add_column(def),
-        ast::AlterTableOperation::RenameColumn{column, to} =>
-            vec![LintError::new(ErrorCode::RenameColumn, format!("Column {} is being renamed to {}, this is never a safe operation.", column, to).as_str())],
-        ast::AlterTableOperation::RenameTable{to} =>
-            vec![LintError::new(ErrorCode::RenameTable, format!("Table {} is being renamed to {} , this is never a safe operation.", name, to).as_str())],
-        _ => Vec::new(),
+/// Returns the length of the token at the start of the given (non-empty) input. Comments and
+/// quoted strings (including dollar-quoted ones, e.g. function bodies) are treated as single
+/// tokens so we never look for semicolons inside them.
+fn token_length(chars: &[char]) -> usize {
+    let rest = &chars[1..];
+    return match chars[0] {
+        '-' if rest.first() == Some(&'-') =>
+            rest.iter().position(|&c| c == '\n').map_or(chars.len(), |n| n + 1),
+        '/' if rest.first() == Some(&'*') =>
+            rest[1..].windows(2).position(|w| w[0] == '*' && w[1] == '/').map_or(chars.len(), |n| n + 4),
+        q @ '\'' | q @ '"' =>
+            rest.iter().position(|&c| c == q).map_or(chars.len(), |n| n + 2),
+        '$' => match dollar_tag_length(chars) {
+            Some(tag) => chars[tag..].windows(tag).position(|w| w == &chars[..tag]).map_or(chars.len(), |n| n + 2 * tag),
+            None => 1,
+        },
+        _ => 1,
     };
 }
 
-fn lint_add_column(def: &ast::ColumnDef) -> Vec<LintError> {
-    return def.options.iter().filter_map(|opt| {
-        match opt.option {
-            ast::ColumnOption::NotNull => Some(LintError::new(ErrorCode::NotNullColumn, format!(
-                "Column {} is added with the NOT NULL option. This can case a full table rewrite which can be very slow.", def.name).as_str())),
-            ast::ColumnOption::Default(_) => Some(LintError::new(ErrorCode::DefaultValue, format!(
-                "Column {} is added with a default value. This can case a full table rewrite which can be very slow.", def.name).as_str())),
-            _ => None,
+/// A token of SQL, with the line and column (both 1-based) it starts at.
+#[derive(Debug, PartialEq)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    /// Returns true if this token is a keyword or identifier, rather than punctuation.
+    /// Quoted identifiers keep their quotes, so they never match a keyword.
+    fn is_word(&self) -> bool {
+        return self.text.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '"');
+    }
+}
+
+/// Splits SQL into tokens: words, quoted identifiers and single characters of punctuation.
+/// Comments and string literals (including dollar-quoted ones) are skipped, in the same way as
+/// when splitting statements, so nothing inside them is mistaken for SQL.
+fn tokens(sql: &str) -> Vec<Token> {
+    let chars = sql.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut column = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let len = if c.is_alphabetic() || c == '_' {
+            chars[i..].iter().position(|&c| !(c.is_alphanumeric() || c == '_' || c == '$')).unwrap_or(chars.len() - i)
+        } else {
+            token_length(&chars[i..])
+        };
+        let skipped = c.is_whitespace() || c == '\'' || is_comment(&chars[i..]) || (c == '$' && len > 1);
+        if !skipped {
+            tokens.push(Token{text: chars[i..i + len].iter().collect(), line: line, column: column});
         }
-    }).collect::<Vec<_>>();
+        for c in &chars[i..i + len] {
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        i += len;
+    }
+    return tokens;
 }
 
-fn lint_create_index(name: &ast::ObjectName, concurrently: bool) -> Vec<LintError> {
-    return if concurrently {
-        Vec::new()
-    } else {
-        vec![LintError::new(ErrorCode::NonConcurrentIndex, format!(
-            "Index {} is created without CONCURRENTLY. This requires holding an exclusive table lock while the index is built, which can cause downtime.", name).as_str())]
+/// Returns the length of the dollar quote tag (e.g. $$ or $body$) at the start of the given
+/// input, or None if it doesn't start with one. Tags can't start with a digit, so that
+/// positional parameters like $1 aren't mistaken for them.
+fn dollar_tag_length(chars: &[char]) -> Option<usize> {
+    let n = chars[1..].iter().position(|&c| c == '$')?;
+    let tag = &chars[1..n + 1];
+    if tag.iter().enumerate().all(|(i, &c)| c == '_' || c.is_alphabetic() || (i > 0 && c.is_ascii_digit())) {
+        return Some(n + 2);
     }
+    return None;
+}
+
+/// The tokens that a column's name follows where it's defined or named in a statement: the start
+/// or a comma in a CREATE TABLE's column list, or the operation in an ALTER TABLE, with or
+/// without COLUMN and IF [NOT] EXISTS.
+const COLUMN_PRECEDERS: &[&str] = &["(", ",", "ADD", "ALTER", "DROP", "RENAME", "COLUMN", "EXISTS"];
+
+/// Returns the line and column (both 1-based) in the given SQL of a statement where the given
+/// column is defined or named, or None if it isn't there. Unquoted names match ignoring case.
+fn column_position(sql: &str, name: &str) -> Option<(usize, usize)> {
+    let tokens = tokens(sql);
+    return tokens.iter().enumerate().skip(1).find(|(i, token)| {
+        let matches = if name.starts_with('"') { token.text == name } else { token.text.eq_ignore_ascii_case(name) };
+        matches && COLUMN_PRECEDERS.iter().any(|p| tokens[i - 1].text.eq_ignore_ascii_case(p))
+    }).map(|(_, token)| (token.line, token.column));
+}
+
+/// Splits SQL into words (including quoted identifiers) and parentheses, dropping comments,
+/// string literals and everything else.
+fn index_tokens(sql: &str) -> Vec<String> {
+    return tokens(sql).into_iter()
+        .filter(|token| token.is_word() || token.text == "(" || token.text == ")")
+        .map(|token| token.text)
+        .collect();
 }
 
 #[cfg(test)]
@@ -110,38 +1823,656 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_create_table() {
-        let errors = lint_errors(&PathBuf::from("test_data/create_table.sql"));
-        assert_eq!(0, errors.len());
+    fn test_lint_syntax_error() {
+        let errors = lint_errors(&PathBuf::from("test_data/syntax_error.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1),
+            LintError::new(ErrorCode::SyntaxError, "").at(2, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(3, 1),
+        ], errors);
+        assert!(errors[1].message().ends_with("in statement: ALTER TABLE characters ADD COLUMN"));
     }
 
     #[test]
-    fn test_lint_add_column_without_default() {
-        let errors = lint_errors(&PathBuf::from("test_data/add_column_without_default.sql"));
-        assert_eq!(0, errors.len());
+    fn test_lint_multiple_syntax_errors() {
+        let errors = lint_errors(&PathBuf::from("test_data/multiple_syntax_errors.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::SyntaxError, "").at(1, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(2, 1),
+            LintError::new(ErrorCode::SyntaxError, "").at(4, 1),
+        ], errors);
+        assert!(errors[2].message().ends_with("in statement: ALTER TABLE characters..."));
+    }
+
+    #[test]
+    fn test_lint_dollar_quoted() {
+        let errors = lint_errors(&PathBuf::from("test_data/dollar_quoted.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::SyntaxError, "").at(1, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(8, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_syntax_error_snippet() {
+        let sql = "UPDATE characters SET name = 'a name that is much too long to quote in full' WHERE";
+        let error = syntax_error(&Unparsed{error: "Expected an expression".to_string(), sql: sql.to_string()});
+        assert_eq!("Expected an expression in statement: UPDATE characters SET name = 'a name that is much too long t...", error.message());
+    }
+
+    #[test]
+    fn test_pg_version() {
+        assert_eq!(Ok(PgVersion(11)), "11".parse());
+        assert_eq!(Ok(PgVersion(9)), "9.6".parse());
+        assert!("eleven".parse::<PgVersion>().is_err());
+        assert!(PgVersion(10) < PgVersion::LATEST);
+    }
+
+    #[test]
+    fn test_lint_ordered() {
+        let files = vec![
+            PathBuf::from("test_data/ordered/0005_not_null.sql"),
+            PathBuf::from("test_data/ordered/0006_backfill.sql"),
+        ];
+        let mut context = LinterContext::new();
+        let errors = files.iter().map(|file| {
+            context.set_file(&display_name(file, None));
+            lint_errors_in(&Linter::new(), file, &Config::default(), &mut context)
+        }).collect::<Vec<_>>();
+        assert_eq!(vec![
+            vec![LintError::new(ErrorCode::SetNotNull, "").at(1, 37)],
+            vec![LintError::new(ErrorCode::NotNullBeforeBackfill, "").at(1, 1)],
+        ], errors);
+        // Each file on its own is fine.
+        assert_eq!(0, lint_errors(&files[1], &Config::default()).len());
+    }
+
+    #[test]
+    fn test_lint_multiple_alter_ops() {
+        let errors = lint_errors(&PathBuf::from("test_data/multiple_alter_ops.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DefaultValue, "").at(2, 14),
+            LintError::new(ErrorCode::SetNotNull, "").at(3, 16),
+            LintError::new(ErrorCode::MultipleAlterOps, "").at(1, 1),
+            LintError::new(ErrorCode::DropColumn, "").at(6, 61),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_multiple_alter_ops_without_locks() {
+        let errors = lint_str("ALTER TABLE characters RENAME COLUMN name TO display_name, DROP COLUMN level;");
+        assert!(!errors.iter().any(|e| e.code() == ErrorCode::MultipleAlterOps));
+        let mut config = Config::default();
+        config.set_enabled(ErrorCode::DefaultValue, false);
+        let errors = lint_with_config("ALTER TABLE characters ADD COLUMN created TIMESTAMP DEFAULT now(), ALTER COLUMN name SET NOT NULL;", &config);
+        assert!(!errors.iter().any(|e| e.code() == ErrorCode::MultipleAlterOps));
+    }
+
+    #[test]
+    fn test_lint_column_position() {
+        // Errors about a column are positioned at it, even on a later line or in a combined ALTER TABLE.
+        let sql = "SET lock_timeout = '1s';\nALTER TABLE characters\n    ADD COLUMN level INT NOT NULL;\nALTER TABLE characters ADD COLUMN a INT, ADD COLUMN Title TEXT NOT NULL;";
+        assert_eq!(vec![
+            LintError::new(ErrorCode::NotNullColumn, "").at(3, 16),
+            LintError::new(ErrorCode::NotNullColumn, "").at(4, 53),
+        ], lint_str(sql));
+    }
+
+    #[test]
+    fn test_column_position() {
+        let sql = "CREATE TABLE guilds (\n  id INT,\n  \"Name\" TEXT\n)";
+        assert_eq!(Some((2, 3)), column_position(sql, "ID"));
+        assert_eq!(Some((3, 3)), column_position(sql, "\"Name\""));
+        assert_eq!(None, column_position(sql, "name"));
+        assert_eq!(Some((1, 42)), column_position("ALTER TABLE guilds DROP COLUMN IF EXISTS id", "id"));
+        // A table with the same name isn't mistaken for the column.
+        assert_eq!(Some((1, 32)), column_position("ALTER TABLE level ALTER COLUMN level SET NOT NULL", "level"));
+    }
+
+    #[test]
+    fn test_split_operations() {
+        assert_eq!(vec![
+            "ALTER TABLE characters ADD COLUMN gold NUMERIC(10, 2)",
+            "ADD CONSTRAINT name_check CHECK (name <> ',')",
+            "DROP COLUMN level",
+        ], split_operations("ALTER TABLE characters ADD COLUMN gold NUMERIC(10, 2),\n  ADD CONSTRAINT name_check CHECK (name <> ','), -- a comment, with a comma\n  DROP COLUMN level"));
+    }
+
+    #[test]
+    fn test_lint_all() {
+        let report = lint_all(&[PathBuf::from("test_data/create_table.sql"), PathBuf::from("test_data/create_index_sync.sql")], &Config::default());
+        assert_eq!(vec![
+            FileResult::new("test_data/create_table.sql", vec![]),
+            FileResult::new("test_data/create_index_sync.sql", vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)]),
+        ], report.files);
+        assert_eq!(1, report.error_count());
+        assert!(!report.passed(&Options::default()));
+        assert!(report.passed(&Options{fail_on: Severity::Error, ..Options::default()}));
+        assert!(report.passed(&Options{count: true, ..Options::default()}));
+        assert_eq!("test_data/create_table.sql\tPASS\ntest_data/create_index_sync.sql\tFAIL\n", report.file_statuses(&Options::default()));
+    }
+
+    #[test]
+    fn test_lint_files_in_order() {
+        let files = fs::read_dir("test_data").unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "sql"))
+            .collect::<Vec<_>>();
+        let parallel = lint_files(&files, &Config::default(), &Options::default());
+        let serial = lint_files(&files, &Config::default(), &Options{parallel: false, ..Options::default()});
+        assert_eq!(files.iter().map(|file| file.to_string_lossy().to_string()).collect::<Vec<_>>(),
+                   parallel.files.iter().map(|result| result.path.clone()).collect::<Vec<_>>());
+        assert_eq!(serial.files, parallel.files);
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let report = |errors| LintReport{files: vec![FileResult::new("a.sql", errors)]};
+        let options = Options::default();
+        assert_eq!(0, report(vec![LintError::new(ErrorCode::RenameTable, "")]).exit_code(&options));
+        assert_eq!(EXIT_WARNINGS, report(vec![LintError::new(ErrorCode::DropColumn, "")]).exit_code(&options));
+        assert_eq!(EXIT_ERRORS, report(vec![
+            LintError::new(ErrorCode::DropColumn, ""),
+            LintError::new(ErrorCode::UnsafeNotNullDefault, ""),
+        ]).exit_code(&options));
+        assert_eq!(EXIT_INTERNAL_ERROR, report(vec![
+            LintError::new(ErrorCode::UnsafeNotNullDefault, ""),
+            LintError::new(ErrorCode::SyntaxError, ""),
+        ]).exit_code(&options));
+        assert_eq!(0, report(vec![LintError::new(ErrorCode::DropColumn, "")]).exit_code(&Options{fail_on: Severity::Error, ..Options::default()}));
+        // Files that couldn't be parsed fail even when nothing else would.
+        let syntax_error = LintError{severity: Severity::Info, ..LintError::new(ErrorCode::SyntaxError, "")};
+        assert_eq!(EXIT_INTERNAL_ERROR, report(vec![syntax_error.clone()]).exit_code(&options));
+        assert_eq!(EXIT_INTERNAL_ERROR, report(vec![LintError::new(ErrorCode::FileError, "")]).exit_code(&Options{fail_on: Severity::Error, max_warnings: Some(5), ..Options::default()}));
+        assert_eq!(0, report(vec![syntax_error]).exit_code(&Options{count: true, ..Options::default()}));
+    }
+
+    #[test]
+    fn test_write_report() {
+        let results = vec![
+            FileResult::new("a.sql", vec![LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(2, 1)]),
+        ];
+        let mut out = Vec::new();
+        write_report(&mut out, &results, &Options{group: false, ..Options::default()}).unwrap();
+        assert_eq!("a.sql:2:1:Warning:DropColumn:Column b is dropped\n", String::from_utf8(out).unwrap());
+        let mut out = Vec::new();
+        write_report(&mut out, &results, &Options{format: Format::Github, ..Options::default()}).unwrap();
+        assert_eq!("::warning file=a.sql,line=2,col=1,title=DropColumn::Column b is dropped\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        let results = vec![
+            FileResult::new("a.sql", vec![
+                LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(2, 1),
+                LintError::new(ErrorCode::DropColumn, "Column c is dropped").at(3, 1),
+            ]),
+            FileResult::new("b.sql", vec![]),
+        ];
+        let mut out = Vec::new();
+        write_report(&mut out, &results, &Options{format: Format::Ndjson, ..Options::default()}).unwrap();
+        assert_eq!(r#"{"file":"a.sql","code":"DropColumn","severity":"Warning","message":"Column b is dropped","line":2,"column":1}
+{"file":"a.sql","code":"DropColumn","severity":"Warning","message":"Column c is dropped","line":3,"column":1}
+"#, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_lint_files_with() {
+        let files = vec![PathBuf::from("test_data/create_table.sql"), PathBuf::from("test_data/create_index_sync.sql")];
+        let seen = Mutex::new(Vec::new());
+        let report = lint_files_with(&files, &Config::default(), &Options::default(), &|result| seen.lock().unwrap().push(result.clone()));
+        // They're seen as each file is finished, so in whatever order that happens.
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut files = report.files.clone();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(files, seen);
+        assert_eq!(2, seen.len());
+    }
+
+    #[test]
+    fn test_write_text_verbose() {
+        let results = vec![
+            FileResult::new("a.sql", vec![LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(2, 1)]),
+        ];
+        let mut out = Vec::new();
+        write_text(&mut out, &results, &Options{group: false, verbose_findings: true, ..Options::default()}).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(Some("a.sql:2:1:Warning:DropColumn:Column b is dropped"), lines.next());
+        let explanation = rule(ErrorCode::DropColumn).explanation;
+        assert_eq!(Some(format!("    {}", explanation.lines().next().unwrap()).as_str()), lines.next());
+        assert_eq!(explanation.lines().count() + 1, out.lines().count());
+    }
+
+    #[test]
+    fn test_file_markers() {
+        let sql = "-- file: 0001_create.sql\nCREATE TABLE a (x INT);\n  --   file: 0002_index.sql  \n-- file:\n";
+        assert_eq!(vec![
+            FileMarker{line: 1, file: "0001_create.sql".to_string()},
+            FileMarker{line: 3, file: "0002_index.sql".to_string()},
+        ], file_markers(sql));
+    }
+
+    #[test]
+    fn test_split_by_markers() {
+        let sql = "CREATE INDEX a_idx ON a (x);\n-- file: 0001_b.sql\nCREATE INDEX b_idx ON b (x);\n-- file: 0002_c.sql\n-- file: 0003_d.sql\n\nALTER TABLE d DROP COLUMN x;\n";
+        let errors = lint_str(sql);
+        assert_eq!(vec![
+            FileResult::new("<stdin>", vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)]),
+            FileResult::new("0001_b.sql", vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)]),
+            FileResult::new("0002_c.sql", vec![]),
+            FileResult::new("0003_d.sql", vec![LintError::new(ErrorCode::DropColumn, "").at(2, 1)]),
+        ], split_by_markers("<stdin>".to_string(), &file_markers(sql), errors));
+    }
+
+    #[test]
+    fn test_split_by_markers_unmarked() {
+        let errors = vec![LintError::new(ErrorCode::DropColumn, "").at(3, 1)];
+        assert_eq!(vec![FileResult::new("<stdin>", errors.clone())], split_by_markers("<stdin>".to_string(), &[], errors));
+    }
+
+    #[test]
+    fn test_error_code_names() {
+        for (i, code) in ErrorCode::ALL.iter().enumerate() {
+            assert_eq!(i, *code as usize, "{} is out of order", code);
+            assert_eq!(Ok(*code), code.to_string().parse::<ErrorCode>());
+            assert_eq!(format!("\"{}\"", code), serde_json::to_string(code).unwrap());
+        }
+        assert_eq!(RULES.len(), ErrorCode::ALL.len());
+        assert_eq!("NonConcurrentIndex", ErrorCode::NonConcurrentIndex.as_str());
+        let err = "NonConcurrentIndexes".parse::<ErrorCode>().unwrap_err();
+        assert!(err.contains("NonConcurrentIndexes"));
+        assert_eq!(Ok(ErrorCode::NonConcurrentIndex), "nonconcurrentindex".parse::<ErrorCode>());
+        assert_eq!(ErrorCode::DropColumn, serde_json::from_str::<ErrorCode>("\"dropcolumn\"").unwrap());
+        let err = serde_json::from_str::<ErrorCode>("\"DropColumns\"").unwrap_err();
+        assert!(err.to_string().contains("see --rules"));
+    }
+
+    #[test]
+    fn test_write_text_grouped() {
+        let results = vec![
+            FileResult::new("a.sql", vec![
+                LintError::new(ErrorCode::DropColumn, "Column b is dropped").at(2, 1),
+                LintError::new(ErrorCode::DropColumn, "Column c is dropped").at(3, 1),
+            ]),
+            FileResult::new("b.sql", vec![]),
+            FileResult::new("c.sql", vec![LintError::new(ErrorCode::SyntaxError, "Expected end of statement").at(1, 1)]),
+        ];
+        let mut out = Vec::new();
+        write_text(&mut out, &results, &Options::default()).unwrap();
+        assert_eq!("a.sql (2 issues)
+a.sql:2:1:Warning:DropColumn:Column b is dropped
+a.sql:3:1:Warning:DropColumn:Column c is dropped
+
+c.sql (1 issue)
+c.sql:1:1:Error:SyntaxError:Expected end of statement
+
+", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_finish_file_sorts() {
+        let errors = vec![
+            LintError::new(ErrorCode::DropColumn, "").at(5, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(2, 3),
+            LintError::new(ErrorCode::DefaultValue, "").at(2, 1),
+        ];
+        let errors = finish_file(FileResult::new("a.sql", errors), &Options::default()).errors;
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DefaultValue, "").at(2, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(2, 3),
+            LintError::new(ErrorCode::DropColumn, "").at(5, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_exit_reason() {
+        let results = vec![
+            FileResult::new("a.sql", vec![
+                LintError::new(ErrorCode::SyntaxError, ""),
+                LintError::new(ErrorCode::DropColumn, ""),
+                LintError::new(ErrorCode::RenameTable, ""),
+            ]),
+            FileResult::new("b.sql", vec![LintError::new(ErrorCode::DropColumn, "")]),
+        ];
+        assert_eq!("FAILED: 1 error, 2 warnings (threshold=warning)", exit_reason(&results, &Options::default()));
+        assert_eq!("FAILED: 1 error, 2 warnings, 1 info (threshold=info, max-warnings=1)",
+                   exit_reason(&results, &Options{fail_on: Severity::Info, max_warnings: Some(1), ..Options::default()}));
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let errors = vec![
+            LintError::new(ErrorCode::DefaultValue, "Column a is added with a volatile default").at(1, 1),
+            LintError::new(ErrorCode::NotNullColumn, "Column a is added with NOT NULL").at(1, 1),
+            LintError::new(ErrorCode::DefaultValue, "Column b is added with a volatile default").at(1, 1),
+            LintError::new(ErrorCode::DefaultValue, "Column c is added with a volatile default").at(2, 1),
+        ];
+        let deduped = dedupe(errors);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DefaultValue, "").at(1, 1),
+            LintError::new(ErrorCode::NotNullColumn, "").at(1, 1),
+            LintError::new(ErrorCode::DefaultValue, "").at(2, 1),
+        ], deduped);
+        assert_eq!("Column a is added with a volatile default (2 occurrences)", deduped[0].message());
+        assert_eq!("Column a is added with NOT NULL", deduped[1].message());
+    }
+
+    #[test]
+    fn test_lint_embedded_sql() {
+        let config = Config::parse("embedded_sql = true").unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/embedded_sql.rb"), &config);
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(6, 7)], errors);
+        let errors = lint_errors(&PathBuf::from("test_data/embedded_sql.py"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(7, 9),
+            LintError::new(ErrorCode::DropColumn, "").at(9, 52),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_disabled_rule() {
+        let config = Config::parse(r#"disabled = ["NonConcurrentIndex"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/multiple_statements.sql"), &config);
+        assert_eq!(vec![LintError::new(ErrorCode::DefaultValue, "").at(6, 35)], errors);
+    }
+
+    #[test]
+    fn test_lint_suppressed() {
+        let errors = lint_errors(&PathBuf::from("test_data/suppressed.sql"), &Config::default());
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(3, 1)], errors);
+    }
+
+    #[test]
+    fn test_suppressions() {
+        let sql = "-- sql-perf-linter: disable=NonConcurrentIndex, DefaultValue\nSELECT 1;\n  -- sql-perf-linter: disable-all\n-- sql-perf-linter: enable\n";
+        assert_eq!(vec![
+            Suppression{line: 1, codes: Some(vec![ErrorCode::NonConcurrentIndex, ErrorCode::DefaultValue])},
+            Suppression{line: 3, codes: None},
+        ], suppressions(sql, &split_statements(sql)).0);
+        let sql = "CREATE INDEX name_idx\n  -- sql-perf-linter: disable-all\n  ON characters (name);\n-- sql-perf-linter: disable-all\n";
+        assert_eq!(vec![Suppression{line: 4, codes: None}], suppressions(sql, &split_statements(sql)).0);
+    }
+
+    #[test]
+    fn test_suppressions_ignore_case() {
+        let sql = "-- sql-perf-linter: disable=nonconcurrentindex\nCREATE INDEX name_idx ON characters (name);\n";
+        assert_eq!(Vec::<LintError>::new(), lint_str(sql));
+    }
+
+    #[test]
+    fn test_suppressions_unknown_rule() {
+        let sql = "  -- sql-perf-linter: disable=NonConcurrentIndx, DefaultValue\nCREATE INDEX name_idx ON characters (name);\n";
+        let (suppressions, unknown) = suppressions(sql, &split_statements(sql));
+        assert_eq!(vec![Suppression{line: 1, codes: Some(vec![ErrorCode::DefaultValue])}], suppressions);
+        assert_eq!(vec![LintError::new(ErrorCode::UnknownSuppression, "").at(1, 3)], unknown);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(2, 1),
+            LintError::new(ErrorCode::UnknownSuppression, "").at(1, 3),
+        ], lint_str(sql));
+        assert!(unknown[0].message().contains("unknown rule NonConcurrentIndx"));
+    }
+
+    #[test]
+    fn test_lint_suppressed_inside_statement() {
+        let errors = lint_str("CREATE INDEX name_idx ON characters (\n  -- sql-perf-linter: disable-all\n  name\n);\nCREATE INDEX level_idx ON characters (level);\n");
+        assert_eq!(vec![
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(5, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_multiple_statements() {
+        let errors = lint_errors(&PathBuf::from("test_data/multiple_statements.sql"), &Config::default());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DefaultValue, "").at(6, 35),
+            LintError::new(ErrorCode::NonConcurrentIndex, "").at(9, 3),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_sql() {
+        let errors = lint_sql(&Linter::new(), "ALTER TABLE characters ADD COLUMN created TIMESTAMP DEFAULT now();".to_string(), &Config::default(), &mut LinterContext::new());
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DefaultValue, "").at(1, 35),
+            LintError::new(ErrorCode::MissingLockTimeout, "").at(1, 1),
+        ], errors);
+    }
+
+    #[test]
+    fn test_lint_small_tables() {
+        let config = Config::parse(r#"small_tables = ["feature_flags", "settings"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/small_tables.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::DropColumn, "").at(2, 34),
+            LintError::new(ErrorCode::NotNullColumn, "").at(4, 35),
+        ], errors);
+        assert_eq!(Some("characters"), errors[1].table());
+    }
+
+    #[test]
+    fn test_lint_cached() {
+        let dir = std::env::temp_dir().join(format!("sql-perf-linter-lint-cached-test-{}", std::process::id()));
+        let cache = Cache::new(dir.clone());
+        let file = PathBuf::from("test_data/small_tables.sql");
+        let config = Config::default();
+        let cold = lint_errors_cached(&Linter::new(), &file, &config, &cache, &mut LinterContext::new());
+        assert!(cache.get(&Cache::key(&fs::read_to_string(&file).unwrap(), &config)).is_some());
+        let warm = lint_errors_cached(&Linter::new(), &file, &config, &cache, &mut LinterContext::new());
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(lint_errors(&file, &config), cold);
+        assert_eq!(cold, warm);
+        let details = |errors: &[LintError]| errors.iter().map(|e| (e.message().to_string(), e.table().map(str::to_string))).collect::<Vec<_>>();
+        assert_eq!(details(&cold), details(&warm));
+        assert_eq!(Some("characters"), warm.last().unwrap().table());
+    }
+
+    #[test]
+    fn test_lint_partitioned_tables() {
+        let config = Config::parse(r#"enabled = ["PartitionedTable"]
+partitioned_tables = ["measurements"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/partitioned_tables.sql"), &config);
+        assert_eq!(vec![
+            LintError::new(ErrorCode::NotNullColumn, "").at(2, 31),
+            LintError::new(ErrorCode::ColumnTypeChange, "").at(3, 39),
+            LintError::new(ErrorCode::NotNullColumn, "").at(4, 35),
+        ], errors);
+        assert_eq!(vec![Severity::Error, Severity::Error, Severity::Warning], errors.iter().map(|e| e.severity()).collect::<Vec<_>>());
+        assert!(errors[0].message().ends_with("events is partitioned, so this applies to every one of its partitions."));
+    }
+
+    #[test]
+    fn test_lint_partitioned_tables_disabled_by_default() {
+        let config = Config::parse(r#"partitioned_tables = ["measurements"]"#).unwrap();
+        let errors = lint_errors(&PathBuf::from("test_data/partitioned_tables.sql"), &config);
+        assert_eq!(3, errors.len());
+        assert!(errors.iter().all(|e| e.severity() == Severity::Warning));
+    }
+
+    #[test]
+    fn test_lint_with_config() {
+        let sql = "SET lock_timeout = '1s';\nUPDATE characters SET level = 1;\nCREATE INDEX name_idx ON characters (name);";
+        let mut config = Config::default();
+        config.set_enabled(ErrorCode::NonConcurrentIndex, false);
+        config.set_severity(ErrorCode::UnboundedWrite, Severity::Error);
+        let errors = lint_with_config(sql, &config);
+        assert_eq!(vec![LintError::new(ErrorCode::UnboundedWrite, "").at(2, 1)], errors);
+        assert_eq!(Severity::Error, errors[0].severity());
+    }
+
+    #[test]
+    fn test_has_findings() {
+        let sql = "SET lock_timeout = '1s';\nCREATE INDEX name_idx ON characters (name);";
+        assert!(has_findings(sql, &Config::default(), Severity::Warning));
+        let mut config = Config::default();
+        config.set_enabled(ErrorCode::NonConcurrentIndex, false);
+        assert!(!has_findings(sql, &config, Severity::Info));
+        let config = Config::parse(r#"enabled = ["EmptyMigration"]"#).unwrap();
+        assert!(has_findings("", &config, Severity::Info));
+        assert!(!has_findings("", &Config::default(), Severity::Info));
+    }
+
+    #[test]
+    fn test_has_findings_below_threshold() {
+        // Only an Info finding, which wouldn't fail with fail_on at Warning.
+        let sql = "SET lock_timeout = '1s';\nCREATE INDEX name_idx ON characters (name);";
+        let mut config = Config::default();
+        config.set_severity(ErrorCode::NonConcurrentIndex, Severity::Info);
+        assert!(!has_findings(sql, &config, Severity::Warning));
+        assert!(has_findings(sql, &config, Severity::Info));
+    }
+
+    #[test]
+    fn test_lint_str() {
+        let errors = lint_str("CREATE INDEX name_idx ON characters (name);");
+        assert_eq!(1, errors.len());
+        assert_eq!(ErrorCode::NonConcurrentIndex, errors[0].code());
+        assert_eq!(1, errors[0].line());
+        assert_eq!(1, errors[0].column());
+        assert!(errors[0].message().contains("name_idx"));
+    }
+
+    #[test]
+    fn test_lint_missing_file() {
+        let errors = lint_file(Path::new("test_data/does_not_exist.sql"));
+        assert_eq!(vec![LintError::new(ErrorCode::FileError, "")], errors);
     }
 
     #[test]
-    fn test_lint_add_column_with_default() {
-        let errors = lint_errors(&PathBuf::from("test_data/add_column_with_default.sql"));
-        assert_eq!(vec![LintError::new(ErrorCode::DefaultValue, "")], errors);
+    fn test_lint_file() {
+        let errors = lint_file(Path::new("test_data/create_index_sync.sql"));
+        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)], errors);
     }
 
     #[test]
-    fn test_lint_create_index_sync() {
-        let errors = lint_errors(&PathBuf::from("test_data/create_index_sync.sql"));
-        assert_eq!(vec![LintError::new(ErrorCode::NonConcurrentIndex, "")], errors);
+    fn test_lint_statements() {
+        let sql = "BEGIN; CREATE INDEX CONCURRENTLY name_idx ON characters (name); COMMIT;";
+        let stmts = Parser::parse_sql(&dialect::PostgreSqlDialect{}, sql.to_string()).unwrap();
+        assert_eq!(vec![LintError::new(ErrorCode::ConcurrentIndexInTransaction, "")], lint_statements(&stmts));
     }
 
     #[test]
-    fn test_lint_create_index_async() {
-        let errors = lint_errors(&PathBuf::from("test_data/create_index_async.sql"));
+    fn test_lint_sql_mysql() {
+        let mut config = Config::default();
+        config.set_dialect(Dialect::MySql);
+        let errors = lint_sql(&Linter::new(), "ALTER TABLE characters ADD COLUMN created TIMESTAMP DEFAULT now();".to_string(), &config, &mut LinterContext::new());
         assert_eq!(0, errors.len());
+        let errors = lint_sql(&Linter::new(), "ALTER TABLE characters ADD COLUMN".to_string(), &config, &mut LinterContext::new());
+        assert_eq!(vec![LintError::new(ErrorCode::SyntaxError, "")], errors);
+    }
+
+    #[test]
+    fn test_json_finding() {
+        let error = LintError::new(ErrorCode::DefaultValue, "Column level is added with a default value.").at(2, 3);
+        let finding = Finding{file: "test.sql", error: &error};
+        assert_eq!(
+            r#"{"file":"test.sql","code":"DefaultValue","severity":"Warning","message":"Column level is added with a default value.","line":2,"column":3}"#,
+            serde_json::to_string(&finding).unwrap());
+    }
+
+    #[test]
+    fn test_json_report() {
+        let error = LintError::new(ErrorCode::DefaultValue, "");
+        let report = JsonReport{tool: "sql-perf-linter", tool_version: VERSION, findings: vec![Finding{file: "test.sql", error: &error}]};
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(VERSION, json["toolVersion"]);
+        assert_eq!("DefaultValue", json["findings"][0]["code"]);
+    }
+
+    #[test]
+    fn test_succeeded() {
+        let errors = vec![
+            LintError::new(ErrorCode::NonConcurrentIndex, ""),
+            LintError::new(ErrorCode::DefaultValue, ""),
+        ];
+        assert!(!succeeded(&errors, Severity::Info));
+        assert!(!succeeded(&errors, Severity::Warning));
+        assert!(succeeded(&errors, Severity::Error));
+        assert!(succeeded(&[], Severity::Info));
+    }
+
+    #[test]
+    fn test_summary() {
+        let results = vec![
+            FileResult::new("a.sql", vec![LintError::new(ErrorCode::NonConcurrentIndex, "")]),
+            FileResult::new("b.sql", Vec::new()),
+            FileResult::new("c.sql", vec![LintError::new(ErrorCode::DefaultValue, ""), LintError::new(ErrorCode::DefaultValue, "")]),
+        ];
+        assert_eq!(Some("3 issues in 2 files (DefaultValue: 2, NonConcurrentIndex: 1)".to_string()), summary(&results));
+        assert_eq!(Some("1 issue in 1 file (NonConcurrentIndex: 1)".to_string()), summary(&results[..1]));
+        assert_eq!(None, summary(&results[1..2]));
+    }
+
+    #[test]
+    fn test_passed_max_warnings() {
+        let results = vec![
+            FileResult::new("a.sql", vec![LintError::new(ErrorCode::NonConcurrentIndex, ""), LintError::new(ErrorCode::DropColumn, "")]),
+        ];
+        assert!(!passed(&results, &Options::default()));
+        assert!(passed(&results, &Options{max_warnings: Some(2), ..Options::default()}));
+        assert!(!passed(&results, &Options{max_warnings: Some(1), ..Options::default()}));
+        let results = vec![FileResult::new("a.sql", vec![LintError::new(ErrorCode::SyntaxError, "")])];
+        assert!(!passed(&results, &Options{max_warnings: Some(2), ..Options::default()}));
+    }
+
+    #[test]
+    fn test_text_line() {
+        let error = LintError::new(ErrorCode::NonConcurrentIndex, "Index name_idx is created without CONCURRENTLY.").at(3, 5);
+        assert_eq!("a.sql:3:5:Warning:NonConcurrentIndex:Index name_idx is created without CONCURRENTLY.", text_line("a.sql", &error, false));
+        let colored = text_line("a.sql", &error, true);
+        assert!(colored.contains("\u{1b}["));
+        assert!(colored.ends_with(":Index name_idx is created without CONCURRENTLY."));
+    }
+
+    #[test]
+    fn test_github_annotation() {
+        let error = LintError::new(ErrorCode::NonConcurrentIndex, "Index name_idx is created without CONCURRENTLY.\n100% bad").at(3, 5);
+        assert_eq!(
+            "::warning file=dir%2C1/a.sql,line=3,col=5,title=NonConcurrentIndex::Index name_idx is created without CONCURRENTLY.%0A100%25 bad",
+            github_annotation("dir,1/a.sql", &error));
+        let error = LintError::new(ErrorCode::SyntaxError, "Expected end of statement");
+        assert_eq!("::error file=a.sql,line=1,col=1,title=SyntaxError::Expected end of statement", github_annotation("a.sql", &error));
+    }
+
+    #[test]
+    fn test_display_name() {
+        assert_eq!("<stdin>", display_name(&PathBuf::from("-"), None));
+        assert_eq!("migrations/1.sql", display_name(&PathBuf::from("-"), Some("migrations/1.sql")));
+        assert_eq!("test_data/create_table.sql", display_name(&PathBuf::from("test_data/create_table.sql"), None));
+        assert_eq!("test_data/create_table.sql", display_name(&PathBuf::from("test_data/create_table.sql"), Some("migrations/1.sql")));
+    }
+
+    #[test]
+    fn test_tokens() {
+        let token = |text: &str, line, column| Token{text: text.to_string(), line: line, column: column};
+        assert_eq!(vec![
+            token("CREATE", 1, 1),
+            token("INDEX", 1, 8),
+            token("\"my idx\"", 1, 14),
+            token("ON", 2, 3),
+            token("t", 2, 6),
+            token("(", 2, 7),
+            token("c", 2, 8),
+            token(")", 2, 9),
+        ], tokens("CREATE INDEX \"my idx\" -- a comment\n  ON t(c) 'a string' $$ a body $$"));
+        assert_eq!(vec!["COPY", "a", "FROM", "STDIN"], index_tokens("COPY a FROM STDIN; -- (\n"));
     }
 
     #[test]
-    fn test_lint_rename_column() {
-        let errors = lint_errors(&PathBuf::from("test_data/rename_table.sql"));
-        assert_eq!(vec![LintError::new(ErrorCode::RenameTable, "")], errors);
+    fn test_split_statements() {
+        let sql = "SELECT 1; -- a comment; with a semicolon\n  SELECT ';' ; /* ; */ SELECT 2;\n";
+        assert_eq!(vec![
+            Chunk{line: 1, column: 1, sql: "SELECT 1".to_string()},
+            Chunk{line: 2, column: 3, sql: "SELECT ';' ".to_string()},
+            Chunk{line: 2, column: 24, sql: "SELECT 2".to_string()},
+        ], split_statements(sql));
+        let sql = "DO $$ BEGIN; END $$; SELECT $body$ $$; $body$, $1; SELECT $1;";
+        assert_eq!(vec![
+            Chunk{line: 1, column: 1, sql: "DO $$ BEGIN; END $$".to_string()},
+            Chunk{line: 1, column: 22, sql: "SELECT $body$ $$; $body$, $1".to_string()},
+            Chunk{line: 1, column: 52, sql: "SELECT $1".to_string()},
+        ], split_statements(sql));
     }
 }
@@ -1,24 +1,310 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 extern crate stderrlog;
 extern crate structopt;
 use structopt::StructOpt;
 use linter;
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "sql-perf-linter", about = "A linter to find potential performance issues in PostgreSQL migrations.")]
+#[structopt(name = "sql-perf-linter", version = linter::VERSION, about = "A linter to find potential performance issues in PostgreSQL migrations.",
+            after_help = "EXIT CODES:
+    0    Nothing was found that fails according to --fail-on and --max-warnings.
+    1    Findings below error severity fail.
+    2    Findings of error severity fail.
+    3    A file couldn't be read or parsed, or the linter couldn't run, e.g. because of an invalid config.")]
 struct Opts {
     #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
     verbose: usize,
+    /// Don't print anything; only set the exit code.
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+    /// Print only the total number of errors found, and exit successfully regardless.
+    #[structopt(long = "count", conflicts_with = "write-baseline")]
+    count: bool,
+    /// Don't print a summary of the errors found at the end.
+    #[structopt(long = "no-summary")]
+    no_summary: bool,
+    /// Format to print errors in.
+    #[structopt(long = "format", default_value = "text", possible_values = &["text", "json", "sarif", "github", "junit", "checkstyle", "ndjson"])]
+    format: linter::Format,
+    /// File to write errors to instead of stdout.
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Print the explanation of each finding's rule underneath it in text output, as --explain does.
+    #[structopt(long = "verbose-findings")]
+    verbose_findings: bool,
+    /// Print text output as a flat list, rather than grouped by file with a header for each.
+    #[structopt(long = "no-group")]
+    no_group: bool,
+    /// When to colour text output.
+    #[structopt(long = "color", default_value = "auto", possible_values = &["auto", "always", "never"])]
+    color: linter::ColorChoice,
+    /// Collapse errors of the same kind on the same line into one, with a count.
+    #[structopt(long = "dedupe")]
+    dedupe: bool,
+    /// Minimum severity of errors that cause the linter to exit unsuccessfully.
+    #[structopt(long = "fail-on", default_value = "warning", possible_values = &["info", "warning", "error"])]
+    fail_on: linter::Severity,
+    /// Always exit successfully, whatever is found. Findings are still printed.
+    /// If both this and --fail are given, the last one wins.
+    #[structopt(long = "no-fail", visible_alias = "exit-zero", overrides_with = "fail")]
+    no_fail: bool,
+    /// Exit unsuccessfully if anything fails according to --fail-on and --max-warnings, as is the
+    /// default. This overrides an earlier --no-fail, e.g. one in a wrapper script's arguments.
+    #[structopt(long = "fail", overrides_with = "no-fail")]
+    fail: bool,
+    /// On failure, print a final line to stderr saying how many of each severity were found and
+    /// what the threshold was.
+    #[structopt(long = "print-exit-reason")]
+    print_exit_reason: bool,
+    /// At the end, print a line to stderr for each file with its path and PASS or FAIL, separated
+    /// by a tab, for scripts that need to know which files failed.
+    #[structopt(long = "per-file-exit")]
+    per_file_exit: bool,
+    /// Fail if more than this many findings of warning severity or above are found.
+    /// When this is given, warnings no longer fail individually and --fail-on only affects errors.
+    #[structopt(long = "max-warnings")]
+    max_warnings: Option<usize>,
+    /// SQL dialect to parse files as. Performance rules are only applied for postgres.
+    #[structopt(long = "dialect", possible_values = &["postgres", "mysql", "sqlite", "generic"])]
+    dialect: Option<linter::Dialect>,
+    /// Only report the given rule. May be given several times. Takes precedence over the config file.
+    #[structopt(long = "select", value_name = "RULE", number_of_values = 1)]
+    select: Vec<linter::ErrorCode>,
+    /// Config file to load. Defaults to .sql-perf-linter.toml in the current directory if it exists.
+    #[structopt(long = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Write a config file listing every rule with its defaults to the --config file (or
+    /// .sql-perf-linter.toml), then exit.
+    #[structopt(long = "config-init")]
+    config_init: bool,
+    /// Overwrite the file written by --config-init if it already exists.
+    #[structopt(long = "force", requires = "config-init")]
+    force: bool,
+    /// Lint files one at a time in the order given (directories in filename order), carrying state
+    /// over between them to find problems that span several migrations.
+    #[structopt(long = "ordered")]
+    ordered: bool,
+    /// Maximum number of files to lint in parallel. Defaults to the number of CPUs.
+    /// Output is in the same order however many there are.
+    #[structopt(short = "j", long = "jobs")]
+    jobs: Option<usize>,
+    /// Lint files one at a time on a single thread. Output is the same as when linting in parallel;
+    /// this is for when the linter itself should be deterministic too.
+    #[structopt(long = "no-parallel", conflicts_with = "jobs")]
+    no_parallel: bool,
+    /// Print how long linting took to stderr, split into parsing and evaluating rules, along with
+    /// the slowest files.
+    #[structopt(long = "timing")]
+    timing: bool,
+    /// Pattern matching files to lint in any directories given. Defaults to all .sql files.
+    #[structopt(long = "glob", parse(try_from_str = glob::Pattern::new))]
+    glob: Option<glob::Pattern>,
+    /// Name to report errors in SQL read from stdin under, e.g. the file an editor is piping in.
+    #[structopt(long = "stdin-filename", value_name = "PATH")]
+    stdin_filename: Option<String>,
+    /// Treat `-- file: <name>` comments in SQL read from stdin as marking the file the statements
+    /// after them came from, and report errors in them against that file.
+    #[structopt(long = "stdin-file-markers")]
+    stdin_file_markers: bool,
+    /// Lint the SQL passed to execute calls in Ruby (Rails) and Python (Alembic) migrations, rather
+    /// than treating them as SQL. Use --glob to find them in directories.
+    #[structopt(long = "embedded-sql")]
+    embedded_sql: bool,
+    /// Directory to cache results in, so files that haven't changed aren't linted again.
+    #[structopt(long = "cache-dir", parse(from_os_str))]
+    cache_dir: Option<PathBuf>,
+    /// Baseline file of existing findings, which are not reported. Only new findings fail.
+    #[structopt(long = "baseline", parse(from_os_str))]
+    baseline: Option<PathBuf>,
+    /// Write all the findings to the --baseline file instead of reporting them.
+    #[structopt(long = "write-baseline", requires = "baseline")]
+    write_baseline: bool,
+    /// Print a detailed explanation of the given rule and exit.
+    #[structopt(long = "explain", value_name = "RULE")]
+    explain: Option<linter::ErrorCode>,
+    /// List all the rules with their default severity and whether they're enabled, then exit.
+    /// Respects --format json; other formats print a text listing.
+    #[structopt(long = "rules")]
+    rules: bool,
+    /// Skip paths matching this pattern (in gitignore syntax) when searching directories. May be
+    /// given several times. Patterns in a .sqllintignore file in the directory also apply.
+    #[structopt(long = "exclude", value_name = "GLOB", number_of_values = 1)]
+    exclude: Vec<String>,
+    /// Only lint files that have changed between this git ref and HEAD (and are under the files or
+    /// directories given, if any).
+    #[structopt(long = "diff", value_name = "BASE")]
+    diff: Option<String>,
+    /// Lint the files once, then again whenever they change, reprinting the report each time.
+    #[structopt(long = "watch", conflicts_with_all = &["diff", "count", "write-baseline"])]
+    watch: bool,
+    /// Files or directories to lint. If none are given, or one is "-", SQL is read from stdin.
     #[structopt(parse(from_os_str))]
     files: Vec<PathBuf>,
 }
 
 fn main() {
-    let opts = Opts::from_args();
+    let mut opts = Opts::from_args();
     stderrlog::new()
         .module(module_path!())
+        .module("linter")
         .verbosity(opts.verbose)
         .init()
         .unwrap();
-    std::process::exit(if linter::lint(opts.files) { 0 } else { 1 });
+    if let Some(code) = opts.explain {
+        println!("{}", linter::rule(code).explain());
+        return;
+    }
+    if opts.rules {
+        linter::print_rules(opts.format);
+        return;
+    }
+    if opts.config_init {
+        let path = opts.config.unwrap_or_else(|| PathBuf::from(linter::DEFAULT_CONFIG_FILE));
+        match linter::Config::init(&path, opts.force) {
+            Ok(()) => eprintln!("Wrote config file {}", path.display()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(linter::EXIT_INTERNAL_ERROR);
+            },
+        }
+        return;
+    }
+    if let Some(jobs) = opts.jobs {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global().unwrap();
+    }
+    let mut config = match linter::Config::load(opts.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(linter::EXIT_INTERNAL_ERROR);
+        },
+    };
+    if let Some(dialect) = opts.dialect {
+        config.set_dialect(dialect);
+    }
+    if opts.embedded_sql {
+        config.set_embedded_sql(true);
+    }
+    if !opts.select.is_empty() {
+        config.select(opts.select);
+    }
+    if config.dialect() != linter::Dialect::Postgres {
+        eprintln!("Note: performance rules are specific to PostgreSQL, only syntax will be checked for this dialect.");
+    }
+    let (baseline, write_baseline) = match opts.baseline {
+        Some(path) if opts.write_baseline => (None, Some(path)),
+        Some(path) => match linter::Baseline::load(&path) {
+            Ok(baseline) => (Some(baseline), None),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(linter::EXIT_INTERNAL_ERROR);
+            },
+        },
+        None => (None, None),
+    };
+    let files = match &opts.diff {
+        Some(base) => match linter::changed_files(base, &opts.files, opts.glob.as_ref()) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(linter::EXIT_INTERNAL_ERROR);
+            },
+        },
+        None => {
+            if opts.files.is_empty() {
+                opts.files.push(PathBuf::from("-"));
+            }
+            linter::expand_paths(opts.files.clone(), opts.glob.as_ref(), &opts.exclude)
+        },
+    };
+    let options = linter::Options{
+        format: opts.format,
+        fail_on: opts.fail_on,
+        quiet: opts.quiet,
+        summary: !opts.no_summary,
+        max_warnings: opts.max_warnings,
+        // Colour codes are only useful when we're writing to a terminal.
+        color: match (opts.color, &opts.output) {
+            (linter::ColorChoice::Auto, Some(_)) => false,
+            (color, _) => color.enabled(),
+        },
+        dedupe: opts.dedupe,
+        stdin_filename: opts.stdin_filename,
+        cache_dir: opts.cache_dir,
+        baseline: baseline,
+        write_baseline: write_baseline,
+        // There's no reason to give if we're going to exit successfully anyway.
+        print_exit_reason: opts.print_exit_reason && !opts.no_fail,
+        ordered: opts.ordered,
+        output: opts.output,
+        group: !opts.no_group,
+        stdin_file_markers: opts.stdin_file_markers,
+        count: opts.count,
+        verbose_findings: opts.verbose_findings,
+        per_file_exit: opts.per_file_exit,
+        parallel: !opts.no_parallel,
+        timing: opts.timing,
+    };
+    if opts.watch {
+        if opts.files.iter().any(|file| file == Path::new("-")) {
+            eprintln!("--watch needs files or directories to watch, so can't read from stdin");
+            std::process::exit(linter::EXIT_INTERNAL_ERROR);
+        }
+        let (paths, glob, exclude) = (&opts.files, opts.glob.as_ref(), &opts.exclude);
+        let expand = || linter::expand_paths(paths.clone(), glob, exclude);
+        if let Err(e) = linter::watch(paths, &config, &options, &expand) {
+            eprintln!("{}", e);
+            std::process::exit(linter::EXIT_INTERNAL_ERROR);
+        }
+        return;
+    }
+    // NDJSON can be written as each file is linted, unless something else is being written instead.
+    let stream = options.format == linter::Format::Ndjson && !options.quiet && !options.count && options.write_baseline.is_none();
+    let report = if stream {
+        linter::lint_files_streaming(&files, &config, &options)
+    } else {
+        let report = linter::lint_files(&files, &config, &options);
+        report.write(&options).map(|()| report)
+    };
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(linter::EXIT_INTERNAL_ERROR);
+        },
+    };
+    let exit_code = report.exit_code(&options);
+    if exit_code != 0 && options.print_exit_reason {
+        eprintln!("{}", report.exit_reason(&options));
+    }
+    if options.per_file_exit {
+        eprint!("{}", report.file_statuses(&options));
+    }
+    std::process::exit(if opts.no_fail { 0 } else { exit_code });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_fail_last_wins() {
+        let parse = |args: &[&str]| Opts::from_iter(["sql-perf-linter"].iter().chain(args));
+        assert!(!parse(&[]).no_fail);
+        assert!(parse(&["--no-fail"]).no_fail);
+        assert!(parse(&["--fail", "--no-fail"]).no_fail);
+        assert!(!parse(&["--no-fail", "--fail"]).no_fail);
+        assert!(parse(&["--no-fail", "--fail"]).fail);
+        assert!(!parse(&["--exit-zero", "--fail", "a.sql"]).no_fail);
+    }
+
+    #[test]
+    fn test_rule_names() {
+        let parse = |args: &[&str]| Opts::from_iter_safe(["sql-perf-linter"].iter().chain(args));
+        let opts = parse(&["--select", "nonconcurrentindex", "--explain", "DROPCOLUMN"]).unwrap();
+        assert_eq!(vec![linter::ErrorCode::NonConcurrentIndex], opts.select);
+        assert_eq!(Some(linter::ErrorCode::DropColumn), opts.explain);
+        let err = parse(&["--explain", "DropColumns"]).unwrap_err();
+        assert!(err.message.contains("Unknown rule DropColumns, see --rules for the list of them"));
+    }
 }
@@ -0,0 +1,630 @@
+//! The registry of rules the linter knows about, with a long-form explanation of each.
+//! This is what `--explain` and `--rules` print and what SARIF output uses to describe its rules.
+use serde::Serialize;
+use crate::{ErrorCode, Format, Severity};
+
+/// A description of a single rule.
+#[derive(Debug)]
+pub struct RuleInfo {
+    /// The code of errors this rule reports.
+    pub code: ErrorCode,
+    /// A one-line summary of what the rule finds.
+    pub summary: &'static str,
+    /// Why it's a problem and what to do instead. May be several paragraphs.
+    pub explanation: &'static str,
+}
+
+impl RuleInfo {
+    /// Returns the name of this rule, as used in config files and suppression comments.
+    pub fn name(&self) -> String {
+        return self.code.to_string();
+    }
+
+    /// Returns the full human-readable explanation of this rule.
+    pub fn explain(&self) -> String {
+        return format!("{} ({:?}, {})\n\n{}\n\n{}",
+                       self.name(),
+                       self.code.severity(),
+                       if self.code.enabled_by_default() { "enabled by default" } else { "disabled by default" },
+                       self.summary,
+                       self.explanation);
+    }
+}
+
+/// The defaults for a rule, as they're printed by `--rules --format json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleDefaults {
+    name: String,
+    severity: Severity,
+    enabled_by_default: bool,
+    summary: &'static str,
+}
+
+/// Prints all the rules with their defaults and summaries in the given format.
+/// Formats other than JSON are specific to lint errors, so they get the text listing.
+pub fn print_rules(format: Format) {
+    match format {
+        Format::Json => {
+            let rules = RULES.iter().map(|rule| RuleDefaults{
+                name: rule.name(),
+                severity: rule.code.severity(),
+                enabled_by_default: rule.code.enabled_by_default(),
+                summary: rule.summary,
+            }).collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&rules).unwrap());
+        },
+        _ => RULES.iter().for_each(|rule| println!("{}", rule.line())),
+    }
+}
+
+impl RuleInfo {
+    /// Returns a single line describing this rule, for the text listing of all of them.
+    fn line(&self) -> String {
+        return format!("{:<30} {:<8} {:<9} {}",
+                       self.name(),
+                       format!("{:?}", self.code.severity()),
+                       if self.code.enabled_by_default() { "enabled" } else { "disabled" },
+                       self.summary);
+    }
+}
+
+/// Returns the rule for the given code.
+pub fn rule(code: ErrorCode) -> &'static RuleInfo {
+    return &RULES[code as usize];
+}
+
+/// Finds a rule by its name, matched in the same way as ErrorCode parses it.
+pub fn find_rule(name: &str) -> Option<&'static RuleInfo> {
+    return name.parse().ok().map(rule);
+}
+
+/// All the rules, in the same order as the ErrorCodes they correspond to.
+pub const RULES: &[RuleInfo] = &[
+    RuleInfo{
+        code: ErrorCode::FileError,
+        summary: "A file couldn't be read.",
+        explanation: "The file doesn't exist, isn't readable, or isn't valid UTF-8. Nothing in it has been linted.",
+    },
+    RuleInfo{
+        code: ErrorCode::SyntaxError,
+        summary: "A statement couldn't be parsed.",
+        explanation: "The linter can only check statements it understands, so nothing in a statement that fails \
+to parse has been checked. Other statements in the same file are still linted.
+
+This may be a genuine mistake in the SQL, or syntax that the parser doesn't support yet. If it's the latter \
+you can suppress it with a `-- sql-perf-linter: disable=SyntaxError` comment before the statement, but bear \
+in mind that it won't be checked for any other problems either.",
+    },
+    RuleInfo{
+        code: ErrorCode::NotNullColumn,
+        summary: "A column is added with NOT NULL.",
+        explanation: "Adding a NOT NULL column to a table that already has rows fails unless it also has a \
+default, since the existing rows would otherwise violate the constraint. With a volatile default, every row \
+has to be rewritten.
+
+Give it a constant default (see NotNullWithDefault), or add the column as nullable, backfill it, and then add \
+the constraint separately once every row has a value (see SetNotNull for how to do that without a long lock).",
+    },
+    RuleInfo{
+        code: ErrorCode::DefaultValue,
+        summary: "A column is added with a volatile default value.",
+        explanation: "Since PostgreSQL 11, adding a column with a constant default is instant. A volatile \
+default, such as now(), random() or a subquery, has to be evaluated for each existing row instead, so the \
+whole table is rewritten while an ACCESS EXCLUSIVE lock is held on it, blocking all reads and writes.
+
+Add the column without a default, then set the default with a separate ALTER COLUMN ... SET DEFAULT (which \
+only applies to new rows) and backfill the existing rows in batches.",
+    },
+    RuleInfo{
+        code: ErrorCode::NonConcurrentIndex,
+        summary: "An index is created without CONCURRENTLY.",
+        explanation: "CREATE INDEX takes a SHARE lock on the table, which blocks all writes to it until the \
+index is built. On a large table that can take a long time.
+
+Use CREATE INDEX CONCURRENTLY instead. It takes longer and can't run inside a transaction block, but it \
+doesn't block writes. If it fails it leaves an INVALID index behind, which should be dropped before retrying.",
+    },
+    RuleInfo{
+        code: ErrorCode::RenameColumn,
+        summary: "A column is renamed.",
+        explanation: "The rename itself is instant, but any running application code that refers to the old \
+name will start failing as soon as it happens, and any code referring to the new name will fail until it does.
+
+Add a new column instead, write to both, backfill it, move reads over to it, and only then drop the old one.",
+    },
+    RuleInfo{
+        code: ErrorCode::RenameTable,
+        summary: "A table is renamed.",
+        explanation: "The rename itself is instant, but any running application code that refers to the old \
+name will start failing as soon as it happens.
+
+Deploy application code that can cope with either name before renaming, or create a view with the old name \
+after renaming so that both work until everything has been moved over.",
+    },
+    RuleInfo{
+        code: ErrorCode::ColumnTypeChange,
+        summary: "A column's type is changed.",
+        explanation: "Changing a column's type usually rewrites the whole table, and any indexes on it, while \
+holding an ACCESS EXCLUSIVE lock that blocks all reads and writes. A few changes, such as increasing the \
+length of a VARCHAR, are safe without a rewrite.
+
+Otherwise, add a new column with the new type, write to both, backfill it, and switch over to it.",
+    },
+    RuleInfo{
+        code: ErrorCode::SetNotNull,
+        summary: "An existing column is set to NOT NULL.",
+        explanation: "SET NOT NULL scans the whole table to check that no rows are null, while holding an \
+ACCESS EXCLUSIVE lock that blocks all reads and writes.
+
+Instead add a CHECK (column IS NOT NULL) NOT VALID constraint, then VALIDATE CONSTRAINT it separately, which \
+only takes a SHARE UPDATE EXCLUSIVE lock. From PostgreSQL 12, SET NOT NULL uses a valid constraint like that \
+to skip the scan, after which the check constraint can be dropped.",
+    },
+    RuleInfo{
+        code: ErrorCode::DropColumn,
+        summary: "A column is dropped.",
+        explanation: "Dropping a column is quick, but any running application code that still refers to it \
+will start failing, including ORMs that select every column by name.
+
+Make sure no deployed code uses the column before dropping it.",
+    },
+    RuleInfo{
+        code: ErrorCode::ForeignKeyNotValidated,
+        summary: "A foreign key constraint is added without NOT VALID.",
+        explanation: "Adding a foreign key checks every existing row, while holding a SHARE ROW EXCLUSIVE lock \
+on both tables that blocks writes to them.
+
+Add the constraint with NOT VALID, which only applies it to new rows, then VALIDATE CONSTRAINT it in a \
+separate statement, which doesn't block writes.",
+    },
+    RuleInfo{
+        code: ErrorCode::CheckConstraintNotValidated,
+        summary: "A CHECK constraint is added without NOT VALID.",
+        explanation: "Adding a CHECK constraint scans every existing row while holding an ACCESS EXCLUSIVE \
+lock that blocks all reads and writes.
+
+Add the constraint with NOT VALID, which only applies it to new rows, then VALIDATE CONSTRAINT it in a \
+separate statement, which doesn't block reads or writes.",
+    },
+    RuleInfo{
+        code: ErrorCode::NonConcurrentIndexDrop,
+        summary: "An index is dropped without CONCURRENTLY.",
+        explanation: "DROP INDEX takes an ACCESS EXCLUSIVE lock on the table, which blocks all reads and writes \
+while it waits for any running queries on the table to finish.
+
+Use DROP INDEX CONCURRENTLY instead, which can't run inside a transaction block but doesn't block anything.",
+    },
+    RuleInfo{
+        code: ErrorCode::ConcurrentIndexInTransaction,
+        summary: "An index is created CONCURRENTLY inside a transaction block.",
+        explanation: "CREATE INDEX CONCURRENTLY can't run inside a transaction block, so this fails at runtime.
+
+Run it outside the transaction. Many migration tools wrap each migration in a transaction by default, so it \
+may need to go in a migration of its own with that turned off.",
+    },
+    RuleInfo{
+        code: ErrorCode::UnboundedWrite,
+        summary: "An UPDATE or DELETE has no WHERE clause.",
+        explanation: "Writing to every row of a table locks all of them until the transaction commits, generates \
+a lot of WAL, and leaves behind a dead copy of every row for vacuum to clean up.
+
+If it really is meant to affect every row, do it in batches, each in its own transaction.",
+    },
+    RuleInfo{
+        code: ErrorCode::DestructiveStatement,
+        summary: "A table is dropped or truncated.",
+        explanation: "This irreversibly deletes all the data in the table, and takes an ACCESS EXCLUSIVE lock \
+on it while doing so.
+
+Make sure this is intended and that nothing still uses the table. Suppress this with a comment once it has \
+been checked.",
+    },
+    RuleInfo{
+        code: ErrorCode::MissingLockTimeout,
+        summary: "A file takes locks without setting lock_timeout first.",
+        explanation: "A statement waiting to acquire a lock blocks every other query that needs a conflicting \
+lock on the same table, even if the statement itself would be quick. If it's stuck behind a long-running query, \
+that can take the application down.
+
+Run SET lock_timeout before the first statement that takes a lock, so it gives up instead and can be retried.",
+    },
+    RuleInfo{
+        code: ErrorCode::PreferIdentity,
+        summary: "A column uses a SERIAL type rather than an identity column.",
+        explanation: "SERIAL columns have a separately owned sequence with awkward permissions and dump \
+behaviour, and are easy to accidentally overflow with the 4-byte serial type.
+
+Use GENERATED BY DEFAULT AS IDENTITY (or ALWAYS) with a bigint column instead.",
+    },
+    RuleInfo{
+        code: ErrorCode::UnboundedVarchar,
+        summary: "A VARCHAR or TEXT column has no maximum length.",
+        explanation: "Columns with no length limit let arbitrarily large values in, which can cause problems \
+far from where they were written.
+
+Give the column a maximum length, or a CHECK constraint on its length. Adding one later to a populated table \
+requires a full scan.",
+    },
+    RuleInfo{
+        code: ErrorCode::HeavyMaintenance,
+        summary: "CLUSTER, VACUUM FULL or REINDEX without CONCURRENTLY is run.",
+        explanation: "CLUSTER and VACUUM FULL rewrite the whole table while holding an ACCESS EXCLUSIVE lock \
+that blocks all reads and writes. REINDEX blocks writes while the index is rebuilt.
+
+Use REINDEX ... CONCURRENTLY (PostgreSQL 12+) instead of REINDEX. For the others, consider an online tool such \
+as pg_repack, or run them in a maintenance window rather than as part of a migration.",
+    },
+    RuleInfo{
+        code: ErrorCode::AddPrimaryKey,
+        summary: "A primary key is added to an existing table without using an existing index.",
+        explanation: "Adding a primary key builds a unique index on the table, which blocks writes until it's \
+done, and checks that the columns are not null while holding an ACCESS EXCLUSIVE lock.
+
+Create a unique index CONCURRENTLY first, then add the primary key with USING INDEX.",
+    },
+    RuleInfo{
+        code: ErrorCode::UnbatchedBackfill,
+        summary: "An INSERT copies the results of an unlimited query into a table.",
+        explanation: "INSERT ... SELECT without a LIMIT copies every matching row in a single transaction. \
+On a large table that can run for hours, holding locks on every inserted row and generating a lot of WAL, \
+which can cause replicas to fall behind.
+
+Copy the rows in batches instead, for example by ranges of the primary key, each in its own transaction.",
+    },
+    RuleInfo{
+        code: ErrorCode::MultipleAlterOps,
+        summary: "Several risky operations are combined in a single ALTER TABLE.",
+        explanation: "All the operations in one ALTER TABLE run under a single lock, which is held until the \
+slowest of them finishes, and if any one of them fails they are all rolled back.
+
+Run each risky operation as its own statement, so each holds its lock for as short a time as possible and a \
+failure in one doesn't undo the others.",
+    },
+    RuleInfo{
+        code: ErrorCode::VolatileSetDefault,
+        summary: "An existing column's default is set to a volatile value.",
+        explanation: "Unlike adding a column with a volatile default, ALTER COLUMN ... SET DEFAULT is cheap: it \
+doesn't rewrite the table, and only applies to rows inserted afterwards. That can be surprising, since existing \
+rows keep whatever value they had, so a later SET NOT NULL fails (or needs a backfill first).
+
+Make sure existing rows are backfilled separately if they need a value.",
+    },
+    RuleInfo{
+        code: ErrorCode::AddGeneratedColumn,
+        summary: "A stored generated column is added to an existing table.",
+        explanation: "Adding a GENERATED ALWAYS AS (...) STORED column computes its value for every existing \
+row, rewriting the whole table while holding an ACCESS EXCLUSIVE lock that blocks all reads and writes, just \
+like adding a column with a volatile default.
+
+Add an ordinary column instead, keep it up to date with a trigger, and backfill the existing rows in batches.",
+    },
+    RuleInfo{
+        code: ErrorCode::NotNullWithDefault,
+        summary: "A column is added with NOT NULL and a constant default.",
+        explanation: "Since PostgreSQL 11, adding a column with a constant default only updates the catalog, so \
+combining it with NOT NULL is fast and is the safe way to add a NOT NULL column to an existing table. On older \
+versions it rewrites the whole table while holding an ACCESS EXCLUSIVE lock.
+
+Nothing needs to change on PostgreSQL 11 or later. On older versions, add the column as nullable, backfill it \
+and then add the constraint separately.",
+    },
+    RuleInfo{
+        code: ErrorCode::UnnamedIndex,
+        summary: "An index is created without a name.",
+        explanation: "Postgres generates a name for indexes that aren't given one, based on the table and \
+columns. Later migrations then have to guess or look up that name to drop or rebuild the index concurrently, \
+and rolling back the migration is harder to script.
+
+Give every index an explicit name.",
+    },
+    RuleInfo{
+        code: ErrorCode::NotNullBeforeBackfill,
+        summary: "A column is backfilled after it was made NOT NULL.",
+        explanation: "Backfilling a column after making it NOT NULL suggests the steps are the wrong way \
+round: on a table with existing rows, adding the constraint either fails or has to scan the table, and the \
+backfill ends up being pointless.
+
+Add the column as nullable, backfill it, and only then add the constraint. With --ordered, this is checked \
+across files as well as within one.",
+    },
+    RuleInfo{
+        code: ErrorCode::ConcurrentIndex,
+        summary: "An index is created CONCURRENTLY, as it should be.",
+        explanation: "This isn't a problem; it's the positive counterpart to NonConcurrentIndex, for anyone who \
+wants confirmation in the output that the linter saw the statement and found it safe.
+
+Bear in mind that CREATE INDEX CONCURRENTLY can't run in a transaction block, and leaves an INVALID index \
+behind if it fails, which needs dropping before retrying.",
+    },
+    RuleInfo{
+        code: ErrorCode::DropNotNull,
+        summary: "An existing column's NOT NULL constraint is dropped.",
+        explanation: "Dropping NOT NULL is instant and takes no long locks, but it silently weakens a guarantee \
+that application code, reports or other constraints may rely on. Once nulls have been written, putting the \
+constraint back requires a backfill and a full scan.
+
+Make sure everything reading the column copes with nulls before dropping the constraint.",
+    },
+    RuleInfo{
+        code: ErrorCode::AddUniqueConstraint,
+        summary: "A UNIQUE constraint is added to an existing table without using an existing index.",
+        explanation: "Adding a UNIQUE constraint builds a unique index on the table, which blocks writes to it \
+until the index is built.
+
+Create a unique index CONCURRENTLY first, then add the constraint with USING INDEX, which just takes over the \
+existing index.",
+    },
+    RuleInfo{
+        code: ErrorCode::IndexMethod,
+        summary: "An index is created with a different method than the one configured for its columns.",
+        explanation: "Some kinds of data are only searched efficiently by particular index methods; a btree \
+index on a tsvector column, for example, won't help full text search at all, where a GIN index would.
+
+This rule is off by default. To use it, enable it and map patterns matching column names to the method their \
+indexes should use in the index_methods section of the config file.",
+    },
+    RuleInfo{
+        code: ErrorCode::InlineColumnForeignKey,
+        summary: "A column is added with an inline foreign key reference.",
+        explanation: "Adding a column with REFERENCES adds a foreign key constraint along with it, which takes \
+a SHARE ROW EXCLUSIVE lock on the referenced table as well as locking the one being altered. There's no way to \
+mark an inline reference NOT VALID, so the locks can't be kept short.
+
+Add the column on its own, then add the foreign key with ADD CONSTRAINT ... NOT VALID, and VALIDATE CONSTRAINT \
+in a later migration.",
+    },
+    RuleInfo{
+        code: ErrorCode::TransactionIncompatible,
+        summary: "A statement that can't run inside a transaction block.",
+        explanation: "Some statements, such as CREATE INDEX CONCURRENTLY and VACUUM, refuse to run inside a \
+transaction block. Many migration tools wrap each migration in a transaction, in which case these fail at runtime.
+
+Put them in a migration of their own with the transaction turned off. This rule is off by default, since \
+whether it applies depends on the migration tool.",
+    },
+    RuleInfo{
+        code: ErrorCode::Custom,
+        summary: "A problem found by a rule added through the library API.",
+        explanation: "The linter itself never reports this; it's for organisation-specific rules added to a \
+Linter with with_check. See the message for what the rule found.",
+    },
+    RuleInfo{
+        code: ErrorCode::EmptyMigration,
+        summary: "A file has no statements in it.",
+        explanation: "A migration that's empty, only has comments in it, or only starts and commits a \
+transaction doesn't do anything. That's usually a mistake, e.g. a file that was generated but never filled in \
+or whose contents were lost in a merge.
+
+This rule is off by default, since some projects deliberately keep placeholder migrations.",
+    },
+    RuleInfo{
+        code: ErrorCode::LockAfterLongWrite,
+        summary: "A statement that takes a table lock comes after a long-running write in the same file.",
+        explanation: "A write with no WHERE clause or LIMIT can run for a long time and holds locks on every row \
+it touches until its transaction commits. If a statement that needs a table lock, such as an ALTER TABLE, comes \
+after it in the same transaction, the lock is held for as long as the rest of the transaction, and anything \
+waiting on it queues up behind.
+
+Put the schema change and the data change in separate migrations, or at least in separate transactions, so \
+the lock is only held briefly.",
+    },
+    RuleInfo{
+        code: ErrorCode::AddColumnSequenceDefault,
+        summary: "A column is added to an existing table with a default taken from a sequence.",
+        explanation: "A default of nextval(...), or a serial type which implies one, gives every existing row a \
+different value. Postgres can't store that in the catalog the way it does a constant default, so it rewrites \
+the whole table while holding an ACCESS EXCLUSIVE lock.
+
+Add the column without a default, set the default afterwards (which only affects new rows), and backfill \
+existing rows from the sequence in batches.",
+    },
+    RuleInfo{
+        code: ErrorCode::DuplicateIndex,
+        summary: "An index is created with the same definition as another one in the same file.",
+        explanation: "Two indexes on the same columns of the same table, using the same method, are \
+redundant. The second one costs time to build (during which the table may be locked), space to store, and \
+slows down every write to the table, while queries only ever need one of them.
+
+Drop one of the two statements. Partial indexes aren't compared, since their predicates may differ.",
+    },
+    RuleInfo{
+        code: ErrorCode::SetTablespace,
+        summary: "A table or index is moved to another tablespace.",
+        explanation: "SET TABLESPACE copies every page of the table or index to the new tablespace, holding \
+an ACCESS EXCLUSIVE lock the whole time, so nothing can read or write it until the copy finishes.
+
+For large tables, use pg_repack (which can move a table to another tablespace while only briefly locking it), \
+or copy the data into a new table in the right tablespace with logical replication and switch over to it.",
+    },
+    RuleInfo{
+        code: ErrorCode::LoadAfterLock,
+        summary: "Data is bulk loaded into a table after a statement that locked it in the same file.",
+        explanation: "ALTER TABLE, TRUNCATE and CREATE INDEX without CONCURRENTLY all lock the table until their \
+transaction commits. A COPY or INSERT ... SELECT into the same table later in that transaction keeps the lock \
+held for as long as the load takes, and every query on the table queues up behind it in the meantime.
+
+Load the data in a separate migration that runs after the DDL has committed. A COMMIT in between is enough \
+for this rule. It's off by default, since it only matters when the load is large.",
+    },
+    RuleInfo{
+        code: ErrorCode::UnsafeNotNullDefault,
+        summary: "A NOT NULL column is added to an existing table with a volatile default.",
+        explanation: "A default that can differ between rows, such as now() or a value from a sequence, can't \
+be stored in the catalog the way a constant one is. Postgres has to evaluate it for every existing row and \
+rewrite the whole table with the results, holding an ACCESS EXCLUSIVE lock the whole time, and the NOT NULL \
+constraint means there's no way to add the column first and fill it in afterwards within the same statement.
+
+This is reported instead of the separate NotNullColumn and DefaultValue findings. Add the column as \
+nullable without a default, set the default (which only affects new rows), backfill existing rows in \
+batches, then add the NOT NULL constraint using a validated CHECK constraint.",
+    },
+    RuleInfo{
+        code: ErrorCode::DropWithoutIfExists,
+        summary: "An object is dropped without IF EXISTS.",
+        explanation: "A DROP without IF EXISTS fails if the object isn't there, so a migration containing one \
+can't be run a second time, e.g. after it failed partway through or against a database that's already had it \
+applied by hand. Adding IF EXISTS makes the statement idempotent.
+
+This is about being able to re-run migrations rather than about what's being dropped; see \
+DestructiveStatement for that. It's off by default, since not every migration tool needs it.",
+    },
+    RuleInfo{
+        code: ErrorCode::CreateTableAsSelect,
+        summary: "A table is created from the results of a query.",
+        explanation: "CREATE TABLE ... AS runs the whole query and writes out its results as part of the \
+migration. On large source tables that can take a long time, generating a lot of WAL, and the tables it reads \
+from hold ACCESS SHARE locks until the transaction commits, so any DDL on them (and everything queued behind \
+that) waits for it.
+
+Create the table empty (or with WITH NO DATA, which isn't reported) and fill it in batches, outside the \
+migration if possible. It's off by default, since it's fine when the source tables are small.",
+    },
+    RuleInfo{
+        code: ErrorCode::DisabledIntegrity,
+        summary: "Triggers or rules on a table are disabled, so they're no longer enforced.",
+        explanation: "ALTER TABLE ... DISABLE TRIGGER stops the trigger firing for every session, not just the \
+migration's, until it's re-enabled. DISABLE TRIGGER ALL includes the internal triggers that enforce foreign \
+keys, so rows that violate them can be written in the meantime and are never checked afterwards. It's easy to \
+forget to re-enable them, or for the migration to fail before it gets that far.
+
+Re-enable them in the same transaction where possible, and check that nothing was written that breaks the \
+constraints they enforce. To skip triggers only for the migration's own writes, SET LOCAL \
+session_replication_role = replica does so without affecting any other sessions (although it skips foreign \
+key checks too).",
+    },
+    RuleInfo{
+        code: ErrorCode::NonConcurrentRefresh,
+        summary: "A materialized view is refreshed without CONCURRENTLY.",
+        explanation: "REFRESH MATERIALIZED VIEW reruns the view's query and replaces its contents while holding \
+an ACCESS EXCLUSIVE lock on it, so nothing can read from the view until the refresh finishes, which takes as \
+long as the query does.
+
+REFRESH MATERIALIZED VIEW CONCURRENTLY only takes an EXCLUSIVE lock, which still allows reads, and updates the \
+view's rows in place. It needs a unique index on the view (covering all its rows, so not a partial one), and \
+can't be used on a view that hasn't been populated yet.",
+    },
+    RuleInfo{
+        code: ErrorCode::PartitionedTable,
+        summary: "A table that locking is a problem for is partitioned, so the lock is taken on every partition.",
+        explanation: "Schema changes to a partitioned table apply to all of its partitions, so anything that \
+rewrites or scans the table does so for every partition, with all of them locked until the last one is \
+done. That's usually far more data than an unpartitioned table holds, and locks that would be brief elsewhere \
+can block writes to the whole table for a long time.
+
+This rule doesn't report anything itself. When it's enabled, findings about locking a table are raised to \
+error severity if the table is partitioned, either because it's listed in partitioned_tables in the config \
+file or because it was created with PARTITION BY in a migration linted earlier (with --ordered, for earlier \
+files). It's off by default, since it changes how other rules are reported.",
+    },
+    RuleInfo{
+        code: ErrorCode::InlinePrimaryKeyColumn,
+        summary: "A column is added to an existing table with an inline PRIMARY KEY.",
+        explanation: "ADD COLUMN ... PRIMARY KEY makes the new column the table's primary key as it's added. \
+That builds a unique index on it, which blocks writes until it's done, and checks that every row has a value \
+while holding an ACCESS EXCLUSIVE lock; with no default that fails outright if the table has any rows.
+
+Add the column without the constraint and backfill it, then create a unique index on it CONCURRENTLY and add \
+the primary key with USING INDEX, as for AddPrimaryKey.",
+    },
+    RuleInfo{
+        code: ErrorCode::ComplexDefault,
+        summary: "A column's default contains a subquery or refers to a column.",
+        explanation: "A column default has to be computable on its own for each new row, so PostgreSQL rejects \
+defaults that contain a subquery or refer to any column, and the migration fails when it gets that far. It's \
+usually an attempt to derive the new column's value from existing data.
+
+Add the column with a plain default (or none), then backfill existing rows with an UPDATE in batches. If new \
+rows need the value derived too, set it in a trigger or in the application, or use a generated column if \
+it only depends on the same row.",
+    },
+    RuleInfo{
+        code: ErrorCode::UnknownSuppression,
+        summary: "A suppression comment names a rule that doesn't exist.",
+        explanation: "A `-- sql-perf-linter: disable=...` comment lists a rule name that the linter doesn't know \
+about, usually because of a typo or a rule that's been renamed. Nothing is suppressed for that name, so the \
+finding it was meant to silence is still reported.
+
+Rule names are matched ignoring case; `sql-perf-linter --rules` lists all of them.",
+    },
+];
+
+/// A kind of statement that can't run inside a transaction block.
+#[derive(Debug)]
+pub(crate) struct TransactionIncompatible {
+    /// How to refer to the statement in messages.
+    pub name: &'static str,
+    /// The words the statement starts with.
+    starts: &'static [&'static str],
+    /// Words that must all appear somewhere after those.
+    contains: &'static [&'static str],
+}
+
+/// The statements that can't run inside a transaction block.
+pub(crate) const TRANSACTION_INCOMPATIBLE: &[TransactionIncompatible] = &[
+    TransactionIncompatible{name: "CREATE INDEX CONCURRENTLY", starts: &["CREATE", "INDEX", "CONCURRENTLY"], contains: &[]},
+    TransactionIncompatible{name: "CREATE INDEX CONCURRENTLY", starts: &["CREATE", "UNIQUE", "INDEX", "CONCURRENTLY"], contains: &[]},
+    TransactionIncompatible{name: "DROP INDEX CONCURRENTLY", starts: &["DROP", "INDEX", "CONCURRENTLY"], contains: &[]},
+    TransactionIncompatible{name: "REINDEX CONCURRENTLY", starts: &["REINDEX"], contains: &["CONCURRENTLY"]},
+    TransactionIncompatible{name: "DETACH PARTITION CONCURRENTLY", starts: &["ALTER", "TABLE"], contains: &["DETACH", "PARTITION", "CONCURRENTLY"]},
+    TransactionIncompatible{name: "VACUUM", starts: &["VACUUM"], contains: &[]},
+    // Allowed from PostgreSQL 12, as long as the new value isn't used in the same transaction.
+    TransactionIncompatible{name: "ALTER TYPE ... ADD VALUE", starts: &["ALTER", "TYPE"], contains: &["ADD", "VALUE"]},
+    TransactionIncompatible{name: "CREATE DATABASE", starts: &["CREATE", "DATABASE"], contains: &[]},
+    TransactionIncompatible{name: "DROP DATABASE", starts: &["DROP", "DATABASE"], contains: &[]},
+    TransactionIncompatible{name: "CREATE TABLESPACE", starts: &["CREATE", "TABLESPACE"], contains: &[]},
+    TransactionIncompatible{name: "DROP TABLESPACE", starts: &["DROP", "TABLESPACE"], contains: &[]},
+    TransactionIncompatible{name: "ALTER SYSTEM", starts: &["ALTER", "SYSTEM"], contains: &[]},
+];
+
+/// Returns the kind of statement that can't run inside a transaction block that the given
+/// (uppercased) words of a statement are, if any.
+pub(crate) fn transaction_incompatible(words: &[&str]) -> Option<&'static TransactionIncompatible> {
+    return TRANSACTION_INCOMPATIBLE.iter().find(|statement| {
+        words.starts_with(statement.starts) &&
+            statement.contains.iter().all(|w| words[statement.starts.len()..].contains(w))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_incompatible() {
+        let name = |words: &[&str]| transaction_incompatible(words).map(|statement| statement.name);
+        assert_eq!(Some("CREATE INDEX CONCURRENTLY"), name(&["CREATE", "UNIQUE", "INDEX", "CONCURRENTLY", "A", "ON", "B", "C"]));
+        assert_eq!(None, name(&["CREATE", "INDEX", "A", "ON", "B", "C"]));
+        assert_eq!(None, name(&["CREATE", "TABLE", "INDEX", "CONCURRENTLY", "A"]));
+        assert_eq!(Some("ALTER TYPE ... ADD VALUE"), name(&["ALTER", "TYPE", "MOOD", "ADD", "VALUE", "HAPPY"]));
+        assert_eq!(None, name(&["ALTER", "TYPE", "MOOD", "RENAME", "TO", "FEELING"]));
+        assert_eq!(Some("VACUUM"), name(&["VACUUM", "ANALYZE", "CHARACTERS"]));
+    }
+
+    #[test]
+    fn test_rules_in_order() {
+        for (i, rule) in RULES.iter().enumerate() {
+            assert_eq!(i, rule.code as usize, "{} is out of order", rule.name());
+        }
+        assert_eq!(ErrorCode::UnknownSuppression as usize + 1, RULES.len());
+    }
+
+    #[test]
+    fn test_find_rule() {
+        assert_eq!(ErrorCode::NonConcurrentIndex, find_rule("NonConcurrentIndex").unwrap().code);
+        assert_eq!(ErrorCode::NonConcurrentIndex, find_rule("nonconcurrentindex").unwrap().code);
+        assert!(find_rule("NonConcurrentIndexes").is_none());
+    }
+
+    #[test]
+    fn test_line() {
+        assert_eq!("UnbatchedBackfill              Warning  disabled  An INSERT copies the results of an unlimited query into a table.",
+                   rule(ErrorCode::UnbatchedBackfill).line());
+    }
+
+    #[test]
+    fn test_explain() {
+        let explanation = rule(ErrorCode::PreferIdentity).explain();
+        assert!(explanation.starts_with("PreferIdentity (Warning, disabled by default)\n\nA column uses a SERIAL type"));
+    }
+}
@@ -0,0 +1,185 @@
+//! Output of lint errors in SARIF format, as consumed by GitHub code scanning among others.
+//! See https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html for the full spec;
+//! we only produce the small subset of it that we need.
+use serde::Serialize;
+use crate::{rules, ErrorCode, FileResult, Severity, VERSION};
+
+/// The top-level SARIF document.
+#[derive(Serialize)]
+pub(crate) struct Log<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run<'a>>,
+}
+
+#[derive(Serialize)]
+struct Run<'a> {
+    tool: Tool<'a>,
+    results: Vec<SarifResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct Tool<'a> {
+    driver: Driver<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver<'a> {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule<'a> {
+    id: String,
+    short_description: Message<'a>,
+    full_description: Message<'a>,
+    #[serde(skip)]
+    code: ErrorCode,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult<'a> {
+    rule_id: String,
+    rule_index: usize,
+    level: &'static str,
+    message: Message<'a>,
+    locations: Vec<Location<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location<'a> {
+    physical_location: PhysicalLocation<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation<'a> {
+    artifact_location: ArtifactLocation<'a>,
+    region: Region,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocation<'a> {
+    uri: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+}
+
+impl<'a> Log<'a> {
+    /// Builds a SARIF log from the errors found in each file.
+    /// There is one rule for each distinct code that was found, described from the rule registry.
+    pub(crate) fn new(results: &'a [FileResult]) -> Log<'a> {
+        let mut rules: Vec<Rule> = Vec::new();
+        let mut sarif_results = Vec::new();
+        for FileResult{path: file, errors} in results {
+            for error in errors {
+                let index = match rules.iter().position(|rule| rule.code == error.code) {
+                    Some(index) => index,
+                    None => {
+                        let rule = rules::rule(error.code);
+                        rules.push(Rule{
+                            id: rule.name(),
+                            short_description: Message{text: rule.summary},
+                            full_description: Message{text: rule.explanation},
+                            code: error.code,
+                        });
+                        rules.len() - 1
+                    },
+                };
+                sarif_results.push(SarifResult{
+                    rule_id: error.code.to_string(),
+                    rule_index: index,
+                    level: level(error.severity),
+                    message: Message{text: &error.message},
+                    locations: vec![Location{
+                        physical_location: PhysicalLocation{
+                            artifact_location: ArtifactLocation{uri: file},
+                            region: Region{start_line: error.line, start_column: error.column},
+                        },
+                    }],
+                });
+            }
+        }
+        return Log{
+            schema: "https://json.schemastore.org/sarif-2.1.0.json",
+            version: "2.1.0",
+            runs: vec![Run{
+                tool: Tool{
+                    driver: Driver{
+                        name: env!("CARGO_PKG_NAME"),
+                        version: VERSION,
+                        information_uri: "https://github.com/peterebden/sql-perf-linter",
+                        rules: rules,
+                    },
+                },
+                results: sarif_results,
+            }],
+        };
+    }
+}
+
+fn level(severity: Severity) -> &'static str {
+    return match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::LintError;
+
+    #[test]
+    fn test_log() {
+        let results = vec![
+            FileResult::new("a.sql", vec![
+                LintError::new(ErrorCode::NonConcurrentIndex, "Index a is created without CONCURRENTLY.").at(1, 1),
+                LintError::new(ErrorCode::SyntaxError, "Expected end of statement").at(3, 2),
+            ]),
+            FileResult::new("b.sql", vec![
+                LintError::new(ErrorCode::NonConcurrentIndex, "Index b is created without CONCURRENTLY.").at(2, 1),
+            ]),
+        ];
+        let log = serde_json::to_value(Log::new(&results)).unwrap();
+        let rules = &log["runs"][0]["tool"]["driver"]["rules"];
+        assert_eq!(2, rules.as_array().unwrap().len());
+        assert_eq!("NonConcurrentIndex", rules[0]["id"]);
+        assert_eq!("An index is created without CONCURRENTLY.", rules[0]["shortDescription"]["text"]);
+        assert_eq!(rules::rule(ErrorCode::NonConcurrentIndex).explanation, rules[0]["fullDescription"]["text"]);
+        assert_eq!("SyntaxError", rules[1]["id"]);
+        assert_eq!(json!({
+            "ruleId": "NonConcurrentIndex",
+            "ruleIndex": 0,
+            "level": "warning",
+            "message": {"text": "Index b is created without CONCURRENTLY."},
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": {"uri": "b.sql"},
+                    "region": {"startLine": 2, "startColumn": 1},
+                },
+            }],
+        }), log["runs"][0]["results"][2]);
+        assert_eq!("error", log["runs"][0]["results"][1]["level"]);
+    }
+}
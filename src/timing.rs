@@ -0,0 +1,71 @@
+//! Timing of where linting spends its time, for working out whether large runs would benefit from
+//! more parallelism or from caching.
+use std::fmt::Write;
+use std::time::Duration;
+use crate::plural;
+
+/// The number of slowest files listed in the report.
+const SLOWEST_FILES: usize = 10;
+
+/// How long linting a single file took.
+#[derive(Debug, Clone)]
+pub(crate) struct FileTiming {
+    /// The name the file's results are reported under.
+    pub path: String,
+    /// The time spent parsing it.
+    pub parse: Duration,
+    /// The time spent evaluating rules against its statements.
+    pub rules: Duration,
+    /// The time spent on it in total, including reading and parsing it.
+    pub total: Duration,
+}
+
+/// Returns a report of how long the given files took, for a run that took the given wall time.
+/// Parsing and rule evaluation are summed over all the files, so when linting in parallel they can
+/// add up to more than the wall time.
+pub(crate) fn report(timings: &[FileTiming], wall: Duration) -> String {
+    let parse = timings.iter().map(|timing| timing.parse).sum::<Duration>();
+    let rules = timings.iter().map(|timing| timing.rules).sum::<Duration>();
+    let mut report = String::new();
+    writeln!(report, "Linted {} in {} (wall time)", plural(timings.len(), "file"), seconds(wall)).unwrap();
+    writeln!(report, "    parsing: {}", seconds(parse)).unwrap();
+    writeln!(report, "    rules:   {}", seconds(rules)).unwrap();
+    let mut slowest = timings.iter().collect::<Vec<_>>();
+    slowest.sort_by(|a, b| b.total.cmp(&a.total));
+    if !slowest.is_empty() {
+        writeln!(report, "Slowest files:").unwrap();
+    }
+    for timing in slowest.into_iter().take(SLOWEST_FILES) {
+        writeln!(report, "    {}  {}", seconds(timing.total), timing.path).unwrap();
+    }
+    return report;
+}
+
+/// Formats a duration as a number of seconds.
+fn seconds(duration: Duration) -> String {
+    return format!("{:.3}s", duration.as_secs_f64());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let timing = |path: &str, parse, rules, total| FileTiming{
+            path: path.to_string(),
+            parse: Duration::from_millis(parse),
+            rules: Duration::from_millis(rules),
+            total: Duration::from_millis(total),
+        };
+        let timings = vec![timing("a.sql", 10, 15, 30), timing("b.sql", 200, 250, 500), timing("c.sql", 0, 0, 20)];
+        assert_eq!("Linted 3 files in 0.400s (wall time)
+    parsing: 0.210s
+    rules:   0.265s
+Slowest files:
+    0.500s  b.sql
+    0.030s  a.sql
+    0.020s  c.sql
+", report(&timings, Duration::from_millis(400)));
+    }
+}
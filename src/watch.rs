@@ -0,0 +1,133 @@
+//! Watch mode, which lints files again whenever they change and reprints the report.
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use crate::{lint_files, Config, LintReport, Options};
+
+/// How long changes have to settle for before we lint again, so that saving several files at once
+/// (or an editor writing a file in several steps) only does so once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Lints the files the given paths expand to, then watches the paths and lints again whenever
+/// anything under them changes, clearing the terminal and printing the new report each time.
+/// Only the files that changed are linted again, unless the options say to lint in order, since
+/// then they depend on one another. Files are expanded again each time so new ones are found.
+/// This only returns if watching fails; Ctrl-C exits, since there's nothing to clean up.
+pub fn watch(paths: &[PathBuf], config: &Config, options: &Options, expand: &dyn Fn() -> Vec<PathBuf>) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::watcher(tx, DEBOUNCE).map_err(|e| format!("Failed to start watching: {}", e))?;
+    for path in paths {
+        watcher.watch(path, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+    }
+    let mut report = lint_files(&expand(), config, options);
+    print(&report, options);
+    loop {
+        let mut changed = Some(HashSet::new());
+        let mut event = rx.recv().map_err(|e| format!("Failed to watch for changes: {}", e))?;
+        loop {
+            add_changes(&mut changed, event);
+            match rx.try_recv() {
+                Ok(next) => event = next,
+                Err(_) => break,
+            }
+        }
+        if changed.as_ref().map_or(false, |changed| changed.is_empty()) {
+            continue;
+        }
+        report = relint(report, &expand(), changed.as_ref(), config, options);
+        print(&report, options);
+    }
+}
+
+/// Clears the terminal (if that's where the report is going) and prints the report to it.
+fn print(report: &LintReport, options: &Options) {
+    if options.output.is_none() && atty::is(atty::Stream::Stdout) {
+        print!("\x1b[2J\x1b[H");
+    }
+    if let Err(e) = report.write(options) {
+        eprintln!("{}", e);
+    }
+    eprintln!("Watching for changes, press Ctrl-C to stop...");
+}
+
+/// Adds the paths the given event says have changed to the set of changed ones.
+/// The set becomes None if anything might have changed.
+fn add_changes(changed: &mut Option<HashSet<PathBuf>>, event: DebouncedEvent) {
+    let paths = match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Remove(path) => vec![path],
+        DebouncedEvent::Rename(from, to) => vec![from, to],
+        // These are only early warnings of events that are reported properly once they settle.
+        DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) | DebouncedEvent::Chmod(_) => Vec::new(),
+        DebouncedEvent::Rescan | DebouncedEvent::Error(..) => {
+            *changed = None;
+            return;
+        },
+    };
+    if let Some(changed) = changed {
+        changed.extend(paths.into_iter().map(|path| fs::canonicalize(&path).unwrap_or(path)));
+    }
+}
+
+/// Lints the changed files again, reusing the previous results for the rest of them.
+/// Everything is linted again if we don't know what's changed, or if linting in order.
+fn relint(previous: LintReport, files: &[PathBuf], changed: Option<&HashSet<PathBuf>>, config: &Config, options: &Options) -> LintReport {
+    let changed = match changed {
+        Some(changed) if !options.ordered => changed,
+        _ => return lint_files(files, config, options),
+    };
+    let mut previous = previous.files;
+    let mut results = files.iter().map(|file| {
+        let path = file.to_string_lossy();
+        let canonical = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+        match previous.iter().position(|result| result.path == path) {
+            Some(i) if !changed.contains(&canonical) => Some(previous.swap_remove(i)),
+            _ => None,
+        }
+    }).collect::<Vec<_>>();
+    let stale = files.iter().zip(&results)
+        .filter(|(_, result)| result.is_none())
+        .map(|(file, _)| file.clone())
+        .collect::<Vec<_>>();
+    let mut linted = lint_files(&stale, config, options).files.into_iter();
+    for result in results.iter_mut().filter(|result| result.is_none()) {
+        *result = linted.next();
+    }
+    return LintReport{files: results.into_iter().flatten().collect()};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorCode, FileResult, LintError};
+
+    #[test]
+    fn test_relint() {
+        let files = vec![PathBuf::from("test_data/create_table.sql"), PathBuf::from("test_data/create_index_sync.sql")];
+        let previous = LintReport{files: vec![
+            FileResult::new("test_data/create_index_sync.sql", vec![]),
+            FileResult::new("test_data/create_table.sql", vec![LintError::new(ErrorCode::DropColumn, "")]),
+        ]};
+        let changed = vec![fs::canonicalize(&files[1]).unwrap()].into_iter().collect::<HashSet<_>>();
+        let report = relint(previous, &files, Some(&changed), &Config::default(), &Options::default());
+        assert_eq!(vec![
+            // This one hasn't changed, so the previous (made up) result is reused.
+            FileResult::new("test_data/create_table.sql", vec![LintError::new(ErrorCode::DropColumn, "")]),
+            FileResult::new("test_data/create_index_sync.sql", vec![LintError::new(ErrorCode::NonConcurrentIndex, "").at(1, 1)]),
+        ], report.files);
+    }
+
+    #[test]
+    fn test_add_changes() {
+        let mut changed = Some(HashSet::new());
+        add_changes(&mut changed, DebouncedEvent::NoticeWrite(PathBuf::from("a.sql")));
+        assert_eq!(Some(0), changed.as_ref().map(|changed| changed.len()));
+        add_changes(&mut changed, DebouncedEvent::Rename(PathBuf::from("a.sql"), PathBuf::from("b.sql")));
+        assert_eq!(Some(2), changed.as_ref().map(|changed| changed.len()));
+        add_changes(&mut changed, DebouncedEvent::Rescan);
+        assert!(changed.is_none());
+    }
+}
@@ -0,0 +1 @@
+ALTER TABLE characters ADD CONSTRAINT level_positive CHECK (level > 0);
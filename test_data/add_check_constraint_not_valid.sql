@@ -0,0 +1,2 @@
+ALTER TABLE characters ADD CONSTRAINT level_positive CHECK (level > 0) NOT VALID;
+ALTER TABLE characters VALIDATE CONSTRAINT level_positive;
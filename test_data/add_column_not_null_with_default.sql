@@ -0,0 +1 @@
+ALTER TABLE characters ADD COLUMN level INT NOT NULL DEFAULT 1;
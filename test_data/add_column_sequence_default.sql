@@ -0,0 +1,3 @@
+ALTER TABLE characters ADD COLUMN position BIGINT DEFAULT nextval('character_positions');
+ALTER TABLE characters ADD COLUMN rank BIGINT NOT NULL DEFAULT nextval('character_ranks');
+ALTER TABLE characters ADD COLUMN number BIGSERIAL;
@@ -0,0 +1 @@
+ALTER TABLE characters ADD COLUMN created TIMESTAMP DEFAULT now();
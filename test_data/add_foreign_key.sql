@@ -0,0 +1 @@
+ALTER TABLE characters ADD CONSTRAINT characters_guild_fk FOREIGN KEY (guild_id) REFERENCES guilds (id);
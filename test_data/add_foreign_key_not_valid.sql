@@ -0,0 +1,2 @@
+ALTER TABLE characters ADD CONSTRAINT characters_guild_fk FOREIGN KEY (guild_id) REFERENCES guilds (id) NOT VALID;
+ALTER TABLE characters VALIDATE CONSTRAINT characters_guild_fk;
@@ -0,0 +1 @@
+ALTER TABLE characters ADD COLUMN full_name TEXT GENERATED ALWAYS AS (first_name || ' ' || last_name) STORED;
@@ -0,0 +1 @@
+ALTER TABLE characters ADD PRIMARY KEY (id);
@@ -0,0 +1,2 @@
+CREATE UNIQUE INDEX CONCURRENTLY characters_id_idx ON characters (id);
+ALTER TABLE characters ADD CONSTRAINT characters_pkey PRIMARY KEY USING INDEX characters_id_idx;
@@ -0,0 +1,2 @@
+CREATE UNIQUE INDEX CONCURRENTLY characters_name_idx ON characters (name);
+ALTER TABLE characters ADD CONSTRAINT characters_name_key UNIQUE USING INDEX characters_name_idx;
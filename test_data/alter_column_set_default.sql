@@ -0,0 +1 @@
+ALTER TABLE characters ALTER COLUMN level SET DEFAULT 1;
@@ -0,0 +1 @@
+ALTER TABLE characters ALTER COLUMN created SET DEFAULT now();
@@ -0,0 +1 @@
+CLUSTER characters USING name_idx;
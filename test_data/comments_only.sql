@@ -0,0 +1,2 @@
+-- TODO: add the guilds table
+/* Nothing here yet. */
@@ -0,0 +1,2 @@
+ALTER TABLE characters ADD COLUMN guild_count INTEGER DEFAULT (SELECT count(*) FROM guilds);
+ALTER TABLE characters ALTER COLUMN display_name SET DEFAULT upper(name);
@@ -0,0 +1,4 @@
+BEGIN;
+ALTER TABLE characters ADD COLUMN level INT;
+COMMIT;
+CREATE INDEX CONCURRENTLY level_idx ON characters (level);
@@ -0,0 +1,3 @@
+BEGIN;
+CREATE INDEX CONCURRENTLY name_idx ON characters (name);
+COMMIT;
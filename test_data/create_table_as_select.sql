@@ -0,0 +1,3 @@
+CREATE TABLE guild_members AS SELECT guild_id, id AS character_id FROM characters;
+CREATE UNLOGGED TABLE IF NOT EXISTS guild_sizes AS (SELECT guild_id, count(*) FROM characters GROUP BY guild_id);
+CREATE TABLE character_names AS SELECT id, name FROM characters WITH NO DATA;
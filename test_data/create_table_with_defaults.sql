@@ -0,0 +1,6 @@
+CREATE TABLE characters(
+   id INT PRIMARY KEY,
+   name TEXT NOT NULL,
+   level INT NOT NULL DEFAULT 1,
+   created TIMESTAMP NOT NULL DEFAULT now()
+);
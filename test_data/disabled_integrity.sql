@@ -0,0 +1,6 @@
+ALTER TABLE characters DISABLE TRIGGER ALL;
+UPDATE characters SET guild_id = NULL WHERE guild_id = 42;
+ALTER TABLE characters DISABLE TRIGGER update_modified;
+ALTER TABLE guilds DISABLE RULE guild_audit;
+ALTER TABLE characters ENABLE TRIGGER ALL;
+ALTER TABLE characters ENABLE TRIGGER update_modified;
@@ -0,0 +1 @@
+DROP INDEX CONCURRENTLY name_idx;
@@ -0,0 +1 @@
+DROP INDEX name_idx;
@@ -0,0 +1 @@
+ALTER TABLE characters ALTER COLUMN name DROP NOT NULL;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS guilds;
+DROP INDEX IF EXISTS name_idx;
@@ -0,0 +1,2 @@
+DROP TABLE guilds;
+DROP INDEX CONCURRENTLY name_idx;
@@ -0,0 +1,5 @@
+CREATE INDEX CONCURRENTLY characters_name_idx ON characters (name);
+CREATE INDEX CONCURRENTLY characters_name_level_idx ON characters (name, level);
+CREATE INDEX CONCURRENTLY guilds_name_idx ON guilds (name);
+CREATE INDEX CONCURRENTLY characters_name_idx2 ON characters (name);
+CREATE INDEX CONCURRENTLY characters_active_name_idx ON characters (name) WHERE active;
@@ -0,0 +1,9 @@
+"""Add an index on character names."""
+from alembic import op
+
+
+def upgrade():
+    op.execute("""
+        CREATE INDEX name_idx ON characters (name)
+    """)
+    op.execute('ALTER TABLE characters DROP COLUMN title')
@@ -0,0 +1,10 @@
+class AddCharacterIndexes < ActiveRecord::Migration[6.1]
+  def up
+    add_column :characters, :title, :string
+
+    execute <<~SQL
+      CREATE INDEX name_idx ON characters (name);
+    SQL
+    execute "CREATE INDEX CONCURRENTLY title_idx ON characters (title)"
+  end
+end
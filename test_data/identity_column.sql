@@ -0,0 +1,4 @@
+CREATE TABLE guilds(
+   id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+   name TEXT
+);
@@ -0,0 +1 @@
+CREATE INDEX CONCURRENTLY name_idx ON characters (name);
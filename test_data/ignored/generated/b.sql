@@ -0,0 +1 @@
+CREATE INDEX name_idx ON characters (name);
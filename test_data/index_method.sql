@@ -0,0 +1,3 @@
+CREATE INDEX CONCURRENTLY characters_bio_tsv_idx ON characters USING gin (bio_tsv);
+CREATE INDEX CONCURRENTLY characters_search_tsv_idx ON characters (search_tsv);
+CREATE INDEX CONCURRENTLY characters_name_idx ON characters (name);
@@ -0,0 +1 @@
+ALTER TABLE characters ADD COLUMN guild_id BIGINT REFERENCES guilds (id);
@@ -0,0 +1,2 @@
+CREATE TABLE guilds (id BIGINT PRIMARY KEY, name VARCHAR(100));
+ALTER TABLE characters ADD COLUMN uuid UUID PRIMARY KEY;
@@ -0,0 +1,4 @@
+INSERT INTO characters (name, level) VALUES ('Frodo', 1), ('Sam', 1);
+
+INSERT INTO archived_characters
+  SELECT * FROM characters WHERE id > 1000 ORDER BY id LIMIT 1000;
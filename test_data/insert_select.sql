@@ -0,0 +1,4 @@
+INSERT INTO archived_characters SELECT * FROM characters WHERE deleted;
+
+INSERT INTO character_names (id, name)
+  SELECT id, name FROM characters;
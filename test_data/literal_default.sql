@@ -0,0 +1,2 @@
+ALTER TABLE characters ADD COLUMN level INTEGER DEFAULT 1;
+ALTER TABLE characters ALTER COLUMN title SET DEFAULT 'adventurer';
@@ -0,0 +1,7 @@
+ALTER TABLE characters ADD COLUMN title TEXT;
+CREATE INDEX guild_name_idx ON guilds (name);
+INSERT INTO characters (name, title) SELECT name, title FROM imported_characters;
+INSERT INTO characters (name) VALUES ('Guybrush');
+COPY guilds (id, name) FROM '/var/lib/imports/guilds.csv' WITH (FORMAT csv);
+COMMIT;
+INSERT INTO guilds (id, name) SELECT id, name FROM imported_guilds;
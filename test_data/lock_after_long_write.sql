@@ -0,0 +1,4 @@
+UPDATE characters SET level = 1;
+UPDATE characters SET name = 'unknown' WHERE name IS NULL;
+ALTER TABLE characters ALTER COLUMN level SET NOT NULL;
+ALTER TABLE characters ALTER COLUMN name SET NOT NULL;
@@ -0,0 +1,3 @@
+SET lock_timeout = '5s';
+ALTER TABLE characters ADD COLUMN level INT;
+ALTER TABLE characters ADD COLUMN guild_id INT;
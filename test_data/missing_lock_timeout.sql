@@ -0,0 +1,4 @@
+SELECT 1;
+ALTER TABLE characters ADD COLUMN level INT;
+ALTER TABLE characters ADD COLUMN guild_id INT;
+SET lock_timeout = '5s';
@@ -0,0 +1,6 @@
+ALTER TABLE characters
+  ADD COLUMN created TIMESTAMP DEFAULT now(),
+  ALTER COLUMN name SET NOT NULL,
+  ADD COLUMN nickname VARCHAR(50);
+
+ALTER TABLE characters ADD COLUMN gold INTEGER, DROP COLUMN level;
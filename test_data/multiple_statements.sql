@@ -0,0 +1,9 @@
+-- Create the table first
+CREATE TABLE characters(
+   id INT PRIMARY KEY,
+   name TEXT
+);
+ALTER TABLE characters ADD COLUMN created TIMESTAMP DEFAULT now();
+
+/* The index isn't built concurrently; this should be flagged. */
+  CREATE INDEX name_idx ON characters (name);
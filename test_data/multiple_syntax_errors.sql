@@ -0,0 +1,5 @@
+ALTER TABLE characters ADD COLUMN;
+CREATE INDEX name_idx ON characters (name);
+
+ALTER TABLE characters
+  ADD CONSTRAINT;
@@ -0,0 +1,2 @@
+ALTER TABLE characters ADD CONSTRAINT level_not_null CHECK (level IS NOT NULL) NOT VALID;
+ALTER TABLE characters VALIDATE CONSTRAINT level_not_null;
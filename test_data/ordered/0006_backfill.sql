@@ -0,0 +1 @@
+UPDATE characters SET name = 'unknown' WHERE name IS NULL;
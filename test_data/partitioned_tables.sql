@@ -0,0 +1,4 @@
+CREATE TABLE events (id BIGINT, created TIMESTAMP) PARTITION BY RANGE (created);
+ALTER TABLE events ADD COLUMN kind INTEGER NOT NULL;
+ALTER TABLE measurements ALTER COLUMN value TYPE BIGINT;
+ALTER TABLE characters ADD COLUMN nickname VARCHAR(50) NOT NULL;
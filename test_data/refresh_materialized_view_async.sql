@@ -0,0 +1 @@
+REFRESH MATERIALIZED VIEW CONCURRENTLY character_stats;
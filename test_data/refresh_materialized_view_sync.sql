@@ -0,0 +1 @@
+REFRESH MATERIALIZED VIEW character_stats;
@@ -0,0 +1 @@
+REINDEX INDEX name_idx;
@@ -0,0 +1 @@
+REINDEX INDEX CONCURRENTLY name_idx;
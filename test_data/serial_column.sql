@@ -0,0 +1,5 @@
+CREATE TABLE guilds(
+   id SERIAL PRIMARY KEY,
+   name TEXT
+);
+ALTER TABLE characters ADD COLUMN guild_id BIGSERIAL;
@@ -0,0 +1,2 @@
+ALTER TABLE characters SET TABLESPACE fast_disks;
+ALTER INDEX characters_name_idx SET TABLESPACE fast_disks;
@@ -0,0 +1,4 @@
+ALTER TABLE feature_flags ADD COLUMN enabled BOOLEAN NOT NULL;
+ALTER TABLE settings DROP COLUMN legacy;
+CREATE INDEX settings_name_idx ON settings (name);
+ALTER TABLE characters ADD COLUMN nickname VARCHAR(50) NOT NULL;
@@ -0,0 +1,5 @@
+-- sql-perf-linter: disable=NonConcurrentIndex
+CREATE INDEX name_idx ON characters (name);
+CREATE INDEX level_idx ON characters (level);
+-- sql-perf-linter: disable-all
+ALTER TABLE characters ADD COLUMN level INT NOT NULL DEFAULT 1;
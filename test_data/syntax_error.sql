@@ -0,0 +1,3 @@
+CREATE INDEX name_idx ON characters (name);
+ALTER TABLE characters ADD COLUMN;
+CREATE INDEX level_idx ON characters (level);
@@ -0,0 +1,6 @@
+CREATE INDEX CONCURRENTLY name_idx ON characters (name);
+DROP INDEX CONCURRENTLY level_idx;
+CREATE INDEX level_idx ON characters (level);
+VACUUM ANALYZE characters;
+ALTER TYPE mood ADD VALUE 'happy';
+ALTER TABLE characters DROP COLUMN mood;
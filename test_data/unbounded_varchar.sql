@@ -0,0 +1,7 @@
+CREATE TABLE guilds(
+   id INT PRIMARY KEY,
+   name VARCHAR,
+   motto CHARACTER VARYING(200),
+   description TEXT
+);
+ALTER TABLE characters ADD COLUMN title CHARACTER VARYING;
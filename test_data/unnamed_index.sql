@@ -0,0 +1,2 @@
+CREATE INDEX CONCURRENTLY name_idx ON characters (name);
+CREATE INDEX CONCURRENTLY ON characters (level);
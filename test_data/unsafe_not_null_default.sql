@@ -0,0 +1,4 @@
+ALTER TABLE characters ADD COLUMN joined TIMESTAMP NOT NULL DEFAULT clock_timestamp();
+ALTER TABLE characters ADD COLUMN last_seen TIMESTAMP DEFAULT clock_timestamp();
+ALTER TABLE characters ADD COLUMN title TEXT NOT NULL;
+ALTER TABLE characters ADD COLUMN level INT NOT NULL DEFAULT 1;
@@ -0,0 +1 @@
+UPDATE characters SET level = 1;
@@ -0,0 +1,2 @@
+VACUUM FULL characters;
+VACUUM (FULL, ANALYZE) guilds;
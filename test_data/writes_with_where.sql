@@ -0,0 +1,2 @@
+UPDATE characters SET level = 1 WHERE id BETWEEN 1 AND 1000;
+DELETE FROM characters WHERE level IS NULL;